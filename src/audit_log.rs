@@ -0,0 +1,119 @@
+use crate::*;
+use std::collections::{HashMap, VecDeque};
+
+// Bounded in-memory record of risk decisions. Entries evicted from the
+// buffer are lost unless a sink is attached, which receives every entry as a
+// JSON line at the time it is recorded.
+#[derive(Debug, Clone)]
+pub struct RiskAuditLog {
+    entries: VecDeque<RiskEvent>,
+    capacity: usize,
+    next_sequence: u64,
+    sink: Option<js_sys::Function>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RiskEventType {
+    CheckPassed,
+    CheckRejected,
+    LimitBreach,
+    LimitChange,
+    KillSwitch,
+    RiskResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskEvent {
+    pub sequence: u64,
+    pub timestamp: f64,
+    pub recorded_at: f64,
+    pub event_type: RiskEventType,
+    pub symbol: Option<String>,
+    pub description: String,
+    pub metrics: HashMap<String, f64>,
+}
+
+impl Default for RiskAuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RiskAuditLog {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: 10000,
+            next_sequence: 0,
+            sink: None,
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        timestamp: f64,
+        event_type: RiskEventType,
+        symbol: Option<&str>,
+        description: String,
+        metrics: HashMap<String, f64>,
+    ) {
+        let event = RiskEvent {
+            sequence: self.next_sequence,
+            timestamp,
+            recorded_at: now(),
+            event_type,
+            symbol: symbol.map(|s| s.to_string()),
+            description,
+            metrics,
+        };
+        self.next_sequence += 1;
+
+        if let Some(sink) = &self.sink {
+            if let Ok(line) = serde_json::to_string(&event) {
+                if sink.call1(&JsValue::NULL, &JsValue::from_str(&line)).is_err() {
+                    console_log!("Risk audit sink failed for event {}", event.sequence);
+                }
+            }
+        }
+
+        self.entries.push_back(event);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    // Events with start_time <= timestamp <= end_time, optionally of one type, oldest first
+    pub fn query(&self, start_time: f64, end_time: f64, event_type: Option<RiskEventType>) -> Vec<RiskEvent> {
+        self.entries.iter()
+            .filter(|event| event.timestamp >= start_time && event.timestamp <= end_time)
+            .filter(|event| event_type.is_none_or(|t| event.event_type == t))
+            .cloned()
+            .collect()
+    }
+
+    pub fn export_json_lines(&self, start_time: f64, end_time: f64, event_type: Option<RiskEventType>) -> String {
+        self.query(start_time, end_time, event_type).iter()
+            .filter_map(|event| serde_json::to_string(event).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    pub fn set_sink(&mut self, sink: Option<js_sys::Function>) {
+        self.sink = sink;
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
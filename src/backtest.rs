@@ -12,6 +12,7 @@ pub struct BacktestEngine {
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct BacktestConfig {
     initial_capital: f64,
     transaction_cost_bps: f64,
@@ -33,6 +34,7 @@ pub struct BacktestResults {
     pub calmar_ratio: f64,
     pub sortino_ratio: f64,
     pub final_capital: f64,
+    pub final_cash: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +50,12 @@ pub struct Trade {
     transaction_costs: f64,
 }
 
+impl Default for BacktestEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BacktestEngine {
     pub fn new() -> Self {
         Self {
@@ -70,6 +78,7 @@ impl BacktestEngine {
                 calmar_ratio: 0.0,
                 sortino_ratio: 0.0,
                 final_capital: 0.0,
+                final_cash: 0.0,
             },
             trade_history: Vec::new(),
             pnl_history: VecDeque::new(),
@@ -82,7 +91,7 @@ impl BacktestEngine {
         historical_data: Vec<MarketData>,
         market_maker: &mut MarketMakerEngine,
         risk_manager: &mut RiskManager,
-        volatility_model: &mut VolatilityModel,
+        volatility_models: &mut VolatilityRegistry,
     ) -> BacktestResults {
         console_log!("Starting backtest with {} data points", historical_data.len());
         
@@ -92,16 +101,27 @@ impl BacktestEngine {
         let mut daily_pnls = Vec::new();
         let mut peak_capital = current_capital;
         let mut max_drawdown: f64 = 0.0;
+        risk_manager.set_starting_equity(current_capital);
         
         for (i, market_data) in historical_data.iter().enumerate() {
-            // Update volatility model
+            // Update this symbol's volatility model
+            let volatility_model = volatility_models.model_mut(&market_data.symbol);
             let volatility = volatility_model.update(market_data.last_price, market_data.timestamp);
+            volatility_model.run_scheduled_refits();
+            market_maker.set_regime_probabilities(&volatility_model.get_regime_state().probabilities);
+            if let Some(event) = volatility_model.take_jump_event() {
+                market_maker.on_jump(&market_data.symbol, &event);
+            }
             
             // Update order book with market data
             order_book.update(market_data);
+            risk_manager.update_market_data(market_data);
+            risk_manager.refit_covariance_models();
+            risk_manager.mark_to_market(&mut positions, market_data.timestamp);
             
             // Generate quotes from market maker
             let quotes = market_maker.generate_quotes(market_data, &order_book, volatility);
+            let quotes = risk_manager.trim_quotes(quotes, &positions);
             
             // Simulate market making activity
             if i > 20 { // Shorter warm-up period
@@ -109,7 +129,6 @@ impl BacktestEngine {
                     market_data,
                     &quotes,
                     &mut positions,
-                    &mut current_capital,
                     market_maker,
                     risk_manager,
                 );
@@ -143,7 +162,7 @@ impl BacktestEngine {
         }
         
         // Calculate final results
-        self.results = self.calculate_performance_metrics(current_capital, &daily_pnls, max_drawdown);
+        self.results = self.calculate_performance_metrics(current_capital, risk_manager.cash_balance(), &daily_pnls, max_drawdown);
         
         console_log!("Backtest completed: Total Return: {:.2}%, Sharpe: {:.3}, Max DD: {:.2}%", 
                     self.results.total_return * 100.0, 
@@ -158,9 +177,8 @@ impl BacktestEngine {
         market_data: &MarketData,
         quotes: &[Quote],
         positions: &mut HashMap<String, Position>,
-        current_capital: &mut f64,
         market_maker: &mut MarketMakerEngine,
-        risk_manager: &RiskManager,
+        risk_manager: &mut RiskManager,
     ) {
         // Simulate quote acceptance/rejection based on market conditions
         let acceptance_probability = self.calculate_quote_acceptance_probability(market_data);
@@ -186,12 +204,14 @@ impl BacktestEngine {
                     price,
                     timestamp: market_data.timestamp,
                     order_type: OrderType::Market,
+                    account: String::new(),
+                    strategy: "market_making".to_string(),
                 };
                 
                 // Check risk limits
-                if risk_manager.validate_order(&order, positions) {
+                if risk_manager.validate_order(&order, positions).is_ok() {
                     // Execute the trade
-                    self.execute_simulated_trade(&order, positions, current_capital, market_maker);
+                    self.execute_simulated_trade(&order, positions, market_maker, risk_manager);
                 }
             }
         }
@@ -222,8 +242,8 @@ impl BacktestEngine {
         &mut self,
         order: &Order,
         positions: &mut HashMap<String, Position>,
-        _current_capital: &mut f64,
         market_maker: &mut MarketMakerEngine,
+        risk_manager: &mut RiskManager,
     ) {
         // Calculate transaction costs
         let notional = order.quantity * order.price;
@@ -236,46 +256,14 @@ impl BacktestEngine {
             OrderSide::Sell => order.price * (1.0 - slippage_factor),
         };
         
-        // Update position
-        let position = positions.entry(order.symbol.clone()).or_insert(Position {
-            symbol: order.symbol.clone(),
-            quantity: 0.0,
-            average_price: 0.0,
-            unrealized_pnl: 0.0,
-            realized_pnl: 0.0,
-        });
-        
         let quantity_change = match order.side {
             OrderSide::Buy => order.quantity,
             OrderSide::Sell => -order.quantity,
         };
-        
-        // Calculate realized PnL if closing position
-        let mut realized_pnl = 0.0;
-        if (position.quantity > 0.0 && quantity_change < 0.0) || 
-           (position.quantity < 0.0 && quantity_change > 0.0) {
-            let closing_quantity = quantity_change.abs().min(position.quantity.abs());
-            realized_pnl = (execution_price - position.average_price) * closing_quantity *
-                          if position.quantity > 0.0 { 1.0 } else { -1.0 };
-        }
-        
-        // Update position
-        if (position.quantity + quantity_change).abs() < 0.001 {
-            // Position closed
-            position.realized_pnl += realized_pnl - transaction_cost;
-            position.quantity = 0.0;
-            position.average_price = 0.0;
-        } else {
-            // Update average price for remaining position
-            let new_quantity = position.quantity + quantity_change;
-            if new_quantity.signum() == quantity_change.signum() {
-                // Adding to position
-                let total_cost = position.quantity * position.average_price + quantity_change * execution_price;
-                position.average_price = total_cost / new_quantity;
-            }
-            position.quantity = new_quantity;
-            position.realized_pnl += realized_pnl - transaction_cost;
-        }
+
+        // Book position, cash and limits through the risk manager's fill path
+        let execution = Order { price: execution_price, ..order.clone() };
+        let realized_pnl = risk_manager.apply_fill(positions, &execution, transaction_cost);
         
         // Record trade
         let trade = Trade {
@@ -317,7 +305,7 @@ impl BacktestEngine {
         total_pnl
     }
 
-    fn calculate_performance_metrics(&mut self, final_capital: f64, daily_pnls: &[f64], max_drawdown: f64) -> BacktestResults {
+    fn calculate_performance_metrics(&mut self, final_capital: f64, final_cash: f64, daily_pnls: &[f64], max_drawdown: f64) -> BacktestResults {
         let total_return = (final_capital - self.config.initial_capital) / self.config.initial_capital;
         
         // Calculate volatility and Sharpe ratio
@@ -415,6 +403,7 @@ impl BacktestEngine {
             calmar_ratio,
             sortino_ratio,
             final_capital,
+            final_cash,
         }
     }
 
@@ -0,0 +1,464 @@
+use crate::*;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum CovarianceMethod {
+    // RiskMetrics exponentially weighted covariance
+    #[default]
+    Ewma,
+    // Dynamic conditional correlation on GARCH(1,1)-standardized returns
+    Dcc,
+    // Equal-weighted covariance over every sample since the symbol appeared
+    Sample,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CovarianceConfig {
+    pub method: CovarianceMethod,
+    pub sampling_interval_ms: f64,
+    pub decay_factor: f64, // EWMA lambda per sampling interval
+    pub dcc_alpha: f64,
+    pub dcc_beta: f64,
+}
+
+impl Default for CovarianceConfig {
+    fn default() -> Self {
+        Self {
+            method: CovarianceMethod::Ewma,
+            sampling_interval_ms: 1000.0,
+            // RiskMetrics uses 0.94 on daily data; one-second samples need a
+            // longer memory (half-life of about two minutes)
+            decay_factor: 0.995,
+            dcc_alpha: 0.02,
+            dcc_beta: 0.97,
+        }
+    }
+}
+
+// Covariance and correlation of per-interval log returns, in estimator symbol order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CovarianceSnapshot {
+    pub symbols: Vec<String>,
+    pub method: CovarianceMethod,
+    pub sampling_interval_ms: f64,
+    pub observations: u64,
+    pub covariance: Vec<Vec<f64>>,
+    pub correlation: Vec<Vec<f64>>,
+}
+
+// Univariate GARCH variance used to standardize one symbol's returns for DCC
+#[derive(Debug, Clone)]
+struct AssetVariance {
+    returns: VecDeque<f64>,
+    model: Option<ConditionalVarianceModel>,
+    variance: f64, // Forecast for the next sample
+    samples_since_fit: usize,
+}
+
+const ASSET_FIT_WINDOW: usize = 500;
+const ASSET_REFIT_INTERVAL: usize = 250;
+const ASSET_MIN_FIT_OBSERVATIONS: usize = 100;
+
+// Online multi-asset covariance. Ticks arrive asynchronously, so prices are
+// sampled on a fixed time grid with previous-tick interpolation: each symbol's
+// price at a grid point is its last traded price at or before it.
+#[derive(Debug, Clone)]
+pub struct CovarianceEstimator {
+    symbols: Vec<String>,
+    last_prices: HashMap<String, f64>,
+    sampled_prices: Vec<Option<f64>>, // Price at the previous grid point, by index
+    next_sample_time: Option<f64>,
+    config: CovarianceConfig,
+    ewma_comoments: Vec<Vec<f64>>,   // Exponentially weighted sums of r_i * r_j
+    ewma_weights: Vec<f64>,          // 1 - lambda^n for each symbol's n samples
+    sample_comoments: Vec<Vec<f64>>, // Equal-weighted sums of r_i * r_j
+    sample_counts: Vec<u64>,
+    asset_variances: Vec<AssetVariance>,
+    dcc_q: Vec<Vec<f64>>,            // Quasi-correlation Q_t
+    dcc_z_comoments: Vec<Vec<f64>>,  // Sums of z_i * z_j for the unconditional Q-bar
+    dcc_counts: Vec<u64>,
+    observation_count: u64,
+    min_observations: u64,
+}
+
+impl Default for CovarianceEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CovarianceEstimator {
+    pub fn new() -> Self {
+        Self {
+            symbols: Vec::new(),
+            last_prices: HashMap::new(),
+            sampled_prices: Vec::new(),
+            next_sample_time: None,
+            config: CovarianceConfig::default(),
+            ewma_comoments: Vec::new(),
+            ewma_weights: Vec::new(),
+            sample_comoments: Vec::new(),
+            sample_counts: Vec::new(),
+            asset_variances: Vec::new(),
+            dcc_q: Vec::new(),
+            dcc_z_comoments: Vec::new(),
+            dcc_counts: Vec::new(),
+            observation_count: 0,
+            min_observations: 30,
+        }
+    }
+
+    // Applies to subsequent samples. Accumulated variances are rescaled to a
+    // new sampling interval so estimates stay in per-interval units.
+    pub fn set_config(&mut self, config: CovarianceConfig) {
+        let dcc_alpha = config.dcc_alpha.max(0.0);
+        let dcc_beta = config.dcc_beta.max(0.0).min(0.999 - dcc_alpha);
+        let sampling_interval_ms = config.sampling_interval_ms.max(1.0);
+        let rescale = sampling_interval_ms / self.config.sampling_interval_ms;
+        if rescale != 1.0 {
+            for comoment in self.ewma_comoments.iter_mut().chain(self.sample_comoments.iter_mut()).flatten() {
+                *comoment *= rescale;
+            }
+            for asset in self.asset_variances.iter_mut() {
+                asset.variance *= rescale;
+                asset.model = None;
+                asset.returns.clear();
+                asset.samples_since_fit = 0;
+            }
+        }
+
+        self.config = CovarianceConfig {
+            sampling_interval_ms,
+            decay_factor: config.decay_factor.clamp(0.5, 0.9999),
+            dcc_alpha,
+            dcc_beta,
+            ..config
+        };
+    }
+
+    pub fn get_config(&self) -> &CovarianceConfig {
+        &self.config
+    }
+
+    // Number of sampling intervals in `horizon_secs`, for scaling the covariance
+    pub fn intervals_in(&self, horizon_secs: f64) -> f64 {
+        horizon_secs * 1000.0 / self.config.sampling_interval_ms
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.observation_count >= self.min_observations
+    }
+
+    fn symbol_index(&mut self, symbol: &str) -> usize {
+        if let Some(index) = self.symbols.iter().position(|s| s == symbol) {
+            return index;
+        }
+
+        self.symbols.push(symbol.to_string());
+        let n = self.symbols.len();
+        self.sampled_prices.push(None);
+        self.ewma_weights.push(0.0);
+        self.sample_counts.push(0);
+        self.dcc_counts.push(0);
+        self.asset_variances.push(AssetVariance {
+            returns: VecDeque::new(),
+            model: None,
+            variance: 0.0,
+            samples_since_fit: 0,
+        });
+        for matrix in [&mut self.ewma_comoments, &mut self.sample_comoments, &mut self.dcc_q, &mut self.dcc_z_comoments] {
+            for row in matrix.iter_mut() {
+                row.push(0.0);
+            }
+            matrix.push(vec![0.0; n]);
+        }
+        self.dcc_q[n - 1][n - 1] = 1.0;
+        n - 1
+    }
+
+    // `timestamp` is in milliseconds. Returns true when the tick closed a
+    // sampling interval and the estimates changed.
+    pub fn update(&mut self, symbol: &str, price: f64, timestamp: f64) -> bool {
+        if price <= 0.0 || !price.is_finite() {
+            return false;
+        }
+
+        // A tick at or past the next grid point closes the interval with the
+        // prices seen before it; the tick itself belongs to the next interval
+        let interval = self.config.sampling_interval_ms;
+        let next_sample_time = *self.next_sample_time.get_or_insert(timestamp + interval);
+        let sampled = timestamp >= next_sample_time;
+        if sampled {
+            let intervals = ((timestamp - next_sample_time) / interval).floor() + 1.0;
+            self.take_sample(intervals);
+            self.next_sample_time = Some(next_sample_time + intervals * interval);
+        }
+
+        self.symbol_index(symbol);
+        self.last_prices.insert(symbol.to_string(), price);
+        sampled
+    }
+
+    // Returns over a gap of several quiet intervals are scaled to one interval
+    fn take_sample(&mut self, intervals: f64) {
+        let last_prices = &self.last_prices;
+        let returns: Vec<Option<f64>> = self.symbols.iter().zip(self.sampled_prices.iter_mut())
+            .map(|(symbol, sampled)| {
+                let price = *last_prices.get(symbol)?;
+                let return_value = sampled.map(|previous| (price / previous).ln() / intervals.sqrt());
+                *sampled = Some(price);
+                return_value
+            })
+            .collect();
+
+        if returns.iter().all(Option::is_none) {
+            return;
+        }
+
+        self.observation_count += 1;
+        self.update_dcc(&returns);
+        self.update_ewma(&returns);
+        self.update_sample(&returns);
+        self.update_asset_variances(&returns);
+    }
+
+    fn update_ewma(&mut self, returns: &[Option<f64>]) {
+        let lambda = self.config.decay_factor;
+        for ((row, weight), r_i) in self.ewma_comoments.iter_mut().zip(self.ewma_weights.iter_mut()).zip(returns) {
+            let Some(r_i) = r_i else { continue };
+            *weight = lambda * *weight + (1.0 - lambda);
+            for (comoment, r_j) in row.iter_mut().zip(returns) {
+                if let Some(r_j) = r_j {
+                    *comoment = lambda * *comoment + (1.0 - lambda) * r_i * r_j;
+                }
+            }
+        }
+    }
+
+    // Intraday mean returns are negligible next to their noise, so comoments
+    // are taken about zero like the EWMA
+    fn update_sample(&mut self, returns: &[Option<f64>]) {
+        for ((row, count), r_i) in self.sample_comoments.iter_mut().zip(self.sample_counts.iter_mut()).zip(returns) {
+            let Some(r_i) = r_i else { continue };
+            *count += 1;
+            for (comoment, r_j) in row.iter_mut().zip(returns) {
+                if let Some(r_j) = r_j {
+                    *comoment += r_i * r_j;
+                }
+            }
+        }
+    }
+
+    // Standardizes by the variance forecast made before this sample, then
+    // Q_t = (1 - a - b) Q-bar + a z z' + b Q_{t-1}. Symbols join only once
+    // their variance rests on enough samples to keep early z outliers out of Q-bar.
+    fn update_dcc(&mut self, returns: &[Option<f64>]) {
+        let min_samples = self.min_observations as usize;
+        let z: Vec<Option<f64>> = returns.iter().zip(&self.asset_variances)
+            .map(|(r, asset)| match r {
+                Some(r) if asset.variance > 0.0 && asset.returns.len() >= min_samples => {
+                    Some(r / asset.variance.sqrt())
+                }
+                _ => None,
+            })
+            .collect();
+
+        for (count, z_i) in self.dcc_counts.iter_mut().zip(&z) {
+            if z_i.is_some() {
+                *count += 1;
+            }
+        }
+
+        let (alpha, beta) = (self.config.dcc_alpha, self.config.dcc_beta);
+        let counts = &self.dcc_counts;
+        for (i, (q_row, z_row)) in self.dcc_q.iter_mut().zip(self.dcc_z_comoments.iter_mut()).enumerate() {
+            let Some(z_i) = z[i] else { continue };
+            for (j, (q, z_comoment)) in q_row.iter_mut().zip(z_row.iter_mut()).enumerate() {
+                let Some(z_j) = z[j] else { continue };
+                *z_comoment += z_i * z_j;
+                let q_bar = *z_comoment / counts[i].min(counts[j]) as f64;
+                *q = (1.0 - alpha - beta) * q_bar + alpha * z_i * z_j + beta * *q;
+            }
+        }
+    }
+
+    // GARCH(1,1) per symbol once it has been fit; EWMA variance before that
+    fn update_asset_variances(&mut self, returns: &[Option<f64>]) {
+        for (i, &r) in returns.iter().enumerate() {
+            let Some(r) = r else { continue };
+            let ewma_variance = self.ewma_covariance(i, i);
+            let asset = &mut self.asset_variances[i];
+
+            asset.returns.push_back(r);
+            if asset.returns.len() > ASSET_FIT_WINDOW {
+                asset.returns.pop_front();
+            }
+            asset.variance = match &asset.model {
+                Some(model) => model.next_variance(r, asset.variance),
+                None => ewma_variance,
+            };
+
+            asset.samples_since_fit += 1;
+        }
+    }
+
+    // Refits each symbol's GARCH model once its schedule is due. Called outside
+    // update so the likelihood optimization stays off the tick path.
+    pub fn refit_asset_variances(&mut self) -> usize {
+        let mut refitted = 0;
+        for asset in self.asset_variances.iter_mut() {
+            if asset.samples_since_fit < ASSET_REFIT_INTERVAL || asset.returns.len() < ASSET_MIN_FIT_OBSERVATIONS {
+                continue;
+            }
+            asset.samples_since_fit = 0;
+            let window: Vec<f64> = asset.returns.iter().copied().collect();
+            let sample_variance = window.iter().map(|r| r * r).sum::<f64>() / window.len() as f64;
+            let initial = asset.model.clone()
+                .unwrap_or_else(|| ConditionalVarianceModel::default_for(GarchVariant::Garch, sample_variance));
+            if let Some(fit) = fit_variance_model(&window, &initial) {
+                asset.variance = window.iter()
+                    .fold(sample_variance, |variance, &r| fit.params.next_variance(r, variance));
+                asset.model = Some(fit.params);
+                refitted += 1;
+            }
+        }
+        refitted
+    }
+
+    fn ewma_covariance(&self, i: usize, j: usize) -> f64 {
+        // The later-starting symbol bounds the pair's effective weight
+        let weight = self.ewma_weights[i].min(self.ewma_weights[j]);
+        if weight <= 0.0 {
+            return 0.0;
+        }
+        self.ewma_comoments[i][j] / weight
+    }
+
+    fn sample_covariance(&self, i: usize, j: usize) -> f64 {
+        let count = self.sample_counts[i].min(self.sample_counts[j]);
+        if count == 0 {
+            return 0.0;
+        }
+        self.sample_comoments[i][j] / count as f64
+    }
+
+    fn dcc_covariance(&self, i: usize, j: usize) -> Option<f64> {
+        let (h_i, h_j) = (self.asset_variances[i].variance, self.asset_variances[j].variance);
+        let (q_ii, q_jj) = (self.dcc_q[i][i], self.dcc_q[j][j]);
+        if h_i <= 0.0 || h_j <= 0.0 || q_ii <= 0.0 || q_jj <= 0.0 || self.dcc_counts[i].min(self.dcc_counts[j]) == 0 {
+            return None;
+        }
+        let correlation = if i == j { 1.0 } else { self.dcc_q[i][j] / (q_ii * q_jj).sqrt() };
+        Some(correlation * (h_i * h_j).sqrt())
+    }
+
+    // Per sampling interval
+    pub fn covariance(&self, i: usize, j: usize) -> f64 {
+        if self.observation_count < 2 {
+            return 0.0;
+        }
+        match self.config.method {
+            CovarianceMethod::Ewma => self.ewma_covariance(i, j),
+            CovarianceMethod::Dcc => self.dcc_covariance(i, j).unwrap_or_else(|| self.ewma_covariance(i, j)),
+            CovarianceMethod::Sample => self.sample_covariance(i, j),
+        }
+    }
+
+    pub fn covariance_matrix(&self) -> Vec<Vec<f64>> {
+        (0..self.symbols.len())
+            .map(|i| (0..self.symbols.len()).map(|j| self.covariance(i, j)).collect())
+            .collect()
+    }
+
+    pub fn correlation(&self, i: usize, j: usize) -> f64 {
+        let denominator = (self.covariance(i, i) * self.covariance(j, j)).sqrt();
+        if denominator > 0.0 {
+            (self.covariance(i, j) / denominator).clamp(-1.0, 1.0)
+        } else if i == j {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    pub fn correlation_matrix(&self) -> HashMap<String, HashMap<String, f64>> {
+        self.symbols.iter().enumerate()
+            .map(|(i, symbol_i)| {
+                let row = self.symbols.iter().enumerate()
+                    .map(|(j, symbol_j)| (symbol_j.clone(), self.correlation(i, j)))
+                    .collect();
+                (symbol_i.clone(), row)
+            })
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> CovarianceSnapshot {
+        let n = self.symbols.len();
+        CovarianceSnapshot {
+            symbols: self.symbols.clone(),
+            method: self.config.method,
+            sampling_interval_ms: self.config.sampling_interval_ms,
+            observations: self.observation_count,
+            covariance: self.covariance_matrix(),
+            correlation: (0..n).map(|i| (0..n).map(|j| self.correlation(i, j)).collect()).collect(),
+        }
+    }
+
+    // Units of `hedge` per unit of `symbol` exposure that minimize the
+    // variance of the combined position: -cov(s, h) / var(h)
+    pub fn minimum_variance_hedge_ratio(&self, symbol: &str, hedge: &str) -> Option<f64> {
+        if !self.is_ready() {
+            return None;
+        }
+        let i = self.symbols.iter().position(|s| s == symbol)?;
+        let j = self.symbols.iter().position(|s| s == hedge)?;
+        let hedge_variance = self.covariance(j, j);
+        if hedge_variance <= 0.0 {
+            return None;
+        }
+        Some(-self.covariance(i, j) / hedge_variance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dcc_settles_on_a_constant_correlation() {
+        let correlation = 0.6;
+        let mut estimator = CovarianceEstimator::new();
+        estimator.set_config(CovarianceConfig { method: CovarianceMethod::Dcc, ..CovarianceConfig::default() });
+
+        let mut rng = SimulationRng::new(23);
+        let (mut price_a, mut price_b) = (100.0, 50.0);
+        let mut settled = Vec::new();
+        for step in 0..4000 {
+            // Volatility triples halfway through; the GARCH standardization
+            // should leave the correlation where it was
+            let volatility = if step < 2000 { 1e-3 } else { 3e-3 };
+            let (z_a, z_b) = (rng.next_normal(), rng.next_normal());
+            price_a *= (volatility * z_a).exp();
+            price_b *= (2.0 * volatility * (correlation * z_a + (1.0 - correlation * correlation).sqrt() * z_b)).exp();
+
+            let timestamp = step as f64 * 1000.0;
+            estimator.update("A", price_a, timestamp);
+            estimator.update("B", price_b, timestamp);
+            estimator.refit_asset_variances();
+            if step >= 1000 {
+                settled.push(estimator.correlation(0, 1));
+            }
+        }
+
+        assert!(estimator.dcc_covariance(0, 1).is_some(), "DCC never took over from the EWMA fallback");
+        assert!(estimator.asset_variances.iter().all(|asset| asset.model.is_some()));
+        let mean = settled.iter().sum::<f64>() / settled.len() as f64;
+        assert!((mean - correlation).abs() < 0.05, "mean DCC correlation {}", mean);
+        assert!(settled.iter().all(|c| (c - correlation).abs() < 0.3), "correlation strayed from {}", correlation);
+    }
+}
@@ -0,0 +1,555 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum GarchVariant {
+    #[default]
+    Garch,
+    GjrGarch,
+    Egarch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GarchParameters {
+    pub omega: f64, // Constant term
+    pub alpha: f64, // ARCH coefficient
+    pub beta: f64,  // GARCH coefficient
+}
+
+// GJR-GARCH: sigma^2 = omega + (alpha + gamma * 1[r < 0]) * r^2 + beta * sigma^2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GjrGarchParameters {
+    pub omega: f64,
+    pub alpha: f64,
+    pub gamma: f64, // Extra ARCH weight on down moves
+    pub beta: f64,
+}
+
+// EGARCH: ln sigma^2 = omega + alpha * (|z| - E|z|) + gamma * z + beta * ln sigma^2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EgarchParameters {
+    pub omega: f64,
+    pub alpha: f64, // Size effect
+    pub gamma: f64, // Sign effect; negative when down moves raise volatility more
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConditionalVarianceModel {
+    Garch(GarchParameters),
+    GjrGarch(GjrGarchParameters),
+    Egarch(EgarchParameters),
+}
+
+// Result of a maximum-likelihood fit over a return window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GarchFit {
+    pub params: ConditionalVarianceModel,
+    pub log_likelihood: f64,
+    pub persistence: f64,
+    pub long_run_variance: f64,
+    pub half_life: f64,
+    pub observations: usize,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+// In-sample fit and out-of-sample one-step forecast loss for one variant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VarianceModelComparison {
+    pub variant: GarchVariant,
+    pub fit: GarchFit,
+    pub aic: f64,
+    pub forecast_qlike: f64, // Mean QLIKE loss on the holdout; lower is better
+}
+
+// Stationarity is enforced by keeping persistence strictly below this bound
+const MAX_PERSISTENCE: f64 = 0.999;
+
+// E|z| for a standard normal
+const MEAN_ABS_NORMAL: f64 = 0.7978845608028654;
+
+impl GarchParameters {
+    pub fn persistence(&self) -> f64 {
+        self.alpha + self.beta
+    }
+
+    pub fn long_run_variance(&self) -> f64 {
+        self.omega / (1.0 - self.persistence()).max(1e-6)
+    }
+
+    // Conditional variance for the next period given the last return and variance
+    pub fn next_variance(&self, last_return: f64, variance: f64) -> f64 {
+        self.omega + self.alpha * last_return * last_return + self.beta * variance
+    }
+}
+
+impl GjrGarchParameters {
+    // Half of returns are down moves under a symmetric innovation distribution
+    pub fn persistence(&self) -> f64 {
+        self.alpha + 0.5 * self.gamma + self.beta
+    }
+
+    pub fn long_run_variance(&self) -> f64 {
+        self.omega / (1.0 - self.persistence()).max(1e-6)
+    }
+
+    pub fn next_variance(&self, last_return: f64, variance: f64) -> f64 {
+        let arch = if last_return < 0.0 { self.alpha + self.gamma } else { self.alpha };
+        self.omega + arch * last_return * last_return + self.beta * variance
+    }
+}
+
+impl EgarchParameters {
+    pub fn persistence(&self) -> f64 {
+        self.beta
+    }
+
+    // Variance of the log-variance shock alpha * (|z| - E|z|) + gamma * z for
+    // standard normal z; the two terms are uncorrelated
+    fn shock_variance(&self) -> f64 {
+        self.alpha * self.alpha * (1.0 - MEAN_ABS_NORMAL * MEAN_ABS_NORMAL) + self.gamma * self.gamma
+    }
+
+    fn long_run_log_variance(&self) -> f64 {
+        self.omega / (1.0 - self.beta).max(1e-6)
+    }
+
+    // Expected variance `steps` periods after one with variance `next_variance`.
+    // ln sigma^2 is treated as normal, so the mean of sigma^2 carries half the
+    // forecast variance of ln sigma^2 on top of exp(E[ln sigma^2]).
+    pub fn forecast_variance(&self, next_variance: f64, steps: f64) -> f64 {
+        let steps = steps.max(0.0);
+        let decay = self.beta.powf(steps);
+        let long_run_log = self.long_run_log_variance();
+        let mean_log = long_run_log + decay * (next_variance.max(1e-300).ln() - long_run_log);
+        let log_variance = self.shock_variance() * (1.0 - decay * decay) / (1.0 - self.beta * self.beta).max(1e-6);
+        (mean_log + 0.5 * log_variance).clamp(-700.0, 700.0).exp()
+    }
+
+    // Unconditional mean of sigma^2, the limit of the forecast
+    pub fn long_run_variance(&self) -> f64 {
+        self.forecast_variance(1.0, f64::INFINITY)
+    }
+
+    pub fn next_variance(&self, last_return: f64, variance: f64) -> f64 {
+        let z = last_return / variance.max(1e-300).sqrt();
+        let log_variance = self.omega + self.alpha * (z.abs() - MEAN_ABS_NORMAL) + self.gamma * z +
+            self.beta * variance.max(1e-300).ln();
+        log_variance.clamp(-700.0, 700.0).exp()
+    }
+}
+
+impl ConditionalVarianceModel {
+    // Textbook starting parameters, scaled to the given variance
+    pub fn default_for(variant: GarchVariant, variance: f64) -> Self {
+        match variant {
+            GarchVariant::Garch => ConditionalVarianceModel::Garch(GarchParameters {
+                omega: variance * 0.05,
+                alpha: 0.1,
+                beta: 0.85,
+            }),
+            GarchVariant::GjrGarch => ConditionalVarianceModel::GjrGarch(GjrGarchParameters {
+                omega: variance * 0.05,
+                alpha: 0.05,
+                gamma: 0.1,
+                beta: 0.85,
+            }),
+            GarchVariant::Egarch => ConditionalVarianceModel::Egarch(EgarchParameters {
+                omega: variance.max(1e-300).ln() * 0.05,
+                alpha: 0.1,
+                gamma: -0.05,
+                beta: 0.95,
+            }),
+        }
+    }
+
+    pub fn variant(&self) -> GarchVariant {
+        match self {
+            ConditionalVarianceModel::Garch(_) => GarchVariant::Garch,
+            ConditionalVarianceModel::GjrGarch(_) => GarchVariant::GjrGarch,
+            ConditionalVarianceModel::Egarch(_) => GarchVariant::Egarch,
+        }
+    }
+
+    pub fn persistence(&self) -> f64 {
+        match self {
+            ConditionalVarianceModel::Garch(p) => p.persistence(),
+            ConditionalVarianceModel::GjrGarch(p) => p.persistence(),
+            ConditionalVarianceModel::Egarch(p) => p.persistence(),
+        }
+    }
+
+    pub fn long_run_variance(&self) -> f64 {
+        match self {
+            ConditionalVarianceModel::Garch(p) => p.long_run_variance(),
+            ConditionalVarianceModel::GjrGarch(p) => p.long_run_variance(),
+            ConditionalVarianceModel::Egarch(p) => p.long_run_variance(),
+        }
+    }
+
+    pub fn next_variance(&self, last_return: f64, variance: f64) -> f64 {
+        match self {
+            ConditionalVarianceModel::Garch(p) => p.next_variance(last_return, variance),
+            ConditionalVarianceModel::GjrGarch(p) => p.next_variance(last_return, variance),
+            ConditionalVarianceModel::Egarch(p) => p.next_variance(last_return, variance),
+        }
+    }
+
+    // Expected variance `steps` periods after the one whose variance is
+    // `next_variance`. GARCH and GJR mean-revert linearly in variance; EGARCH
+    // mean-reverts in log variance.
+    pub fn forecast_variance(&self, next_variance: f64, steps: f64) -> f64 {
+        match self {
+            ConditionalVarianceModel::Egarch(p) => p.forecast_variance(next_variance, steps),
+            _ => {
+                let decay = self.persistence().powf(steps.max(0.0));
+                let long_run = self.long_run_variance();
+                long_run + decay * (next_variance - long_run)
+            }
+        }
+    }
+
+    fn parameter_count(&self) -> usize {
+        match self {
+            ConditionalVarianceModel::Garch(_) => 3,
+            _ => 4,
+        }
+    }
+
+    // Unconstrained optimizer coordinates -> positive, stationary parameters
+    fn from_unconstrained(variant: GarchVariant, x: &[f64]) -> Self {
+        match variant {
+            GarchVariant::Garch => {
+                let persistence = MAX_PERSISTENCE * sigmoid(x[1]);
+                let arch_share = sigmoid(x[2]);
+                ConditionalVarianceModel::Garch(GarchParameters {
+                    omega: x[0].exp(),
+                    alpha: persistence * arch_share,
+                    beta: persistence * (1.0 - arch_share),
+                })
+            }
+            GarchVariant::GjrGarch => {
+                // Persistence split between alpha, gamma / 2 and beta
+                let persistence = MAX_PERSISTENCE * sigmoid(x[1]);
+                let weights = [x[2].exp(), x[3].exp(), 1.0];
+                let total: f64 = weights.iter().sum();
+                ConditionalVarianceModel::GjrGarch(GjrGarchParameters {
+                    omega: x[0].exp(),
+                    alpha: persistence * weights[0] / total,
+                    gamma: 2.0 * persistence * weights[1] / total,
+                    beta: persistence * weights[2] / total,
+                })
+            }
+            GarchVariant::Egarch => ConditionalVarianceModel::Egarch(EgarchParameters {
+                omega: x[0],
+                alpha: x[1],
+                gamma: x[2],
+                beta: MAX_PERSISTENCE * x[3].tanh(),
+            }),
+        }
+    }
+
+    fn to_unconstrained(&self) -> Vec<f64> {
+        match self {
+            ConditionalVarianceModel::Garch(p) => {
+                let persistence = p.persistence().clamp(1e-6, MAX_PERSISTENCE - 1e-6);
+                let arch_share = (p.alpha / persistence).clamp(1e-6, 1.0 - 1e-6);
+                vec![p.omega.max(1e-300).ln(), logit(persistence / MAX_PERSISTENCE), logit(arch_share)]
+            }
+            ConditionalVarianceModel::GjrGarch(p) => {
+                let persistence = p.persistence().clamp(1e-6, MAX_PERSISTENCE - 1e-6);
+                let beta = p.beta.max(1e-6);
+                vec![
+                    p.omega.max(1e-300).ln(),
+                    logit(persistence / MAX_PERSISTENCE),
+                    (p.alpha.max(1e-6) / beta).ln(),
+                    (0.5 * p.gamma.max(1e-6) / beta).ln(),
+                ]
+            }
+            ConditionalVarianceModel::Egarch(p) => {
+                let beta = (p.beta / MAX_PERSISTENCE).clamp(-1.0 + 1e-9, 1.0 - 1e-9);
+                vec![p.omega, p.alpha, p.gamma, beta.atanh()]
+            }
+        }
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn logit(p: f64) -> f64 {
+    (p / (1.0 - p)).ln()
+}
+
+// Gaussian log-likelihood of demeaned returns under the given model, with the
+// recursion started at the sample variance
+pub fn garch_log_likelihood(model: &ConditionalVarianceModel, returns: &[f64]) -> f64 {
+    let sample_variance = returns.iter().map(|r| r * r).sum::<f64>() / returns.len().max(1) as f64;
+    let mut variance = sample_variance;
+    let mut log_likelihood = 0.0;
+
+    for (i, &r) in returns.iter().enumerate() {
+        if i > 0 {
+            variance = model.next_variance(returns[i - 1], variance);
+        }
+        if variance <= 0.0 || !variance.is_finite() {
+            return f64::NEG_INFINITY;
+        }
+        log_likelihood -= 0.5 * ((2.0 * std::f64::consts::PI).ln() + variance.ln() + r * r / variance);
+    }
+
+    log_likelihood
+}
+
+// Fits the variant of `initial` by maximum likelihood, starting from its
+// parameters. Returns None for windows too short or too flat to identify them.
+pub fn fit_variance_model(returns: &[f64], initial: &ConditionalVarianceModel) -> Option<GarchFit> {
+    if returns.len() < 30 {
+        return None;
+    }
+
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let demeaned: Vec<f64> = returns.iter().map(|r| r - mean).collect();
+    let sample_variance = demeaned.iter().map(|r| r * r).sum::<f64>() / demeaned.len() as f64;
+    if sample_variance <= 0.0 {
+        return None;
+    }
+
+    // Keep the previous dynamics but rescale the level to this window's variance
+    let variant = initial.variant();
+    let start = match initial {
+        ConditionalVarianceModel::Garch(p) => {
+            let persistence = p.persistence().clamp(0.05, 0.98);
+            let alpha = p.alpha.max(0.01).min(persistence * 0.9);
+            ConditionalVarianceModel::Garch(GarchParameters {
+                omega: sample_variance * (1.0 - persistence),
+                alpha,
+                beta: persistence - alpha,
+            })
+        }
+        ConditionalVarianceModel::GjrGarch(p) => {
+            let scale = p.persistence().clamp(0.05, 0.98) / p.persistence().max(1e-6);
+            ConditionalVarianceModel::GjrGarch(GjrGarchParameters {
+                omega: sample_variance * (1.0 - p.persistence().clamp(0.05, 0.98)),
+                alpha: p.alpha * scale,
+                gamma: p.gamma * scale,
+                beta: p.beta * scale,
+            })
+        }
+        ConditionalVarianceModel::Egarch(p) => {
+            let beta = p.beta.clamp(0.05, 0.98);
+            ConditionalVarianceModel::Egarch(EgarchParameters {
+                omega: sample_variance.ln() * (1.0 - beta),
+                alpha: p.alpha,
+                gamma: p.gamma,
+                beta,
+            })
+        }
+    };
+
+    let objective = |x: &[f64]| {
+        let model = ConditionalVarianceModel::from_unconstrained(variant, x);
+        let log_likelihood = garch_log_likelihood(&model, &demeaned);
+        if log_likelihood.is_finite() { -log_likelihood } else { f64::MAX }
+    };
+    let result = nelder_mead(&objective, &start.to_unconstrained(), 0.5, 1000, 1e-10);
+    let params = ConditionalVarianceModel::from_unconstrained(variant, &result.point);
+    let persistence = params.persistence();
+
+    Some(GarchFit {
+        long_run_variance: params.long_run_variance(),
+        half_life: if persistence > 0.0 { 0.5f64.ln() / persistence.ln() } else { 0.0 },
+        persistence,
+        log_likelihood: -result.value,
+        observations: demeaned.len(),
+        iterations: result.iterations,
+        converged: result.converged,
+        params,
+    })
+}
+
+// Fits every variant on the first two thirds of the window and scores its
+// one-step variance forecasts on the rest with the QLIKE loss
+pub fn compare_variance_models(returns: &[f64]) -> Vec<VarianceModelComparison> {
+    // The fits run on demeaned returns, so the holdout is scored net of the
+    // training mean as well
+    let split = returns.len() * 2 / 3;
+    let mean = returns[..split].iter().sum::<f64>() / split.max(1) as f64;
+    let demeaned: Vec<f64> = returns.iter().map(|r| r - mean).collect();
+    let (training, holdout) = demeaned.split_at(split);
+    let sample_variance = training.iter().map(|r| r * r).sum::<f64>() / training.len().max(1) as f64;
+
+    [GarchVariant::Garch, GarchVariant::GjrGarch, GarchVariant::Egarch].iter()
+        .filter_map(|&variant| {
+            let fit = fit_variance_model(training, &ConditionalVarianceModel::default_for(variant, sample_variance))?;
+            let mut variance = training.iter()
+                .fold(sample_variance, |variance, &r| fit.params.next_variance(r, variance));
+            let mut loss = 0.0;
+            for &r in holdout {
+                loss += r * r / variance + variance.ln();
+                variance = fit.params.next_variance(r, variance);
+            }
+
+            Some(VarianceModelComparison {
+                variant,
+                aic: 2.0 * fit.params.parameter_count() as f64 - 2.0 * fit.log_likelihood,
+                forecast_qlike: loss / holdout.len().max(1) as f64,
+                fit,
+            })
+        })
+        .collect()
+}
+
+pub struct NelderMeadResult {
+    pub point: Vec<f64>,
+    pub value: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+// Derivative-free simplex minimizer used by the likelihood fits
+pub fn nelder_mead(f: &dyn Fn(&[f64]) -> f64, start: &[f64], step: f64, max_iterations: usize, tolerance: f64) -> NelderMeadResult {
+    let n = start.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = Vec::with_capacity(n + 1);
+    simplex.push((start.to_vec(), f(start)));
+    for i in 0..n {
+        let mut point = start.to_vec();
+        point[i] += step;
+        let value = f(&point);
+        simplex.push((point, value));
+    }
+
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < max_iterations {
+        iterations += 1;
+        simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let best = simplex[0].1;
+        let worst = simplex[n].1;
+        if (worst - best).abs() <= tolerance * (best.abs() + tolerance) {
+            converged = true;
+            break;
+        }
+
+        let centroid: Vec<f64> = (0..n)
+            .map(|j| simplex[..n].iter().map(|(point, _)| point[j]).sum::<f64>() / n as f64)
+            .collect();
+        let towards = |coefficient: f64| -> Vec<f64> {
+            centroid.iter().zip(simplex[n].0.iter())
+                .map(|(c, w)| c + coefficient * (w - c))
+                .collect()
+        };
+
+        let reflected = towards(-1.0);
+        let reflected_value = f(&reflected);
+        if reflected_value < best {
+            let expanded = towards(-2.0);
+            let expanded_value = f(&expanded);
+            simplex[n] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[n - 1].1 {
+            simplex[n] = (reflected, reflected_value);
+        } else {
+            let contracted = if reflected_value < worst { towards(-0.5) } else { towards(0.5) };
+            let contracted_value = f(&contracted);
+            if contracted_value < worst.min(reflected_value) {
+                simplex[n] = (contracted, contracted_value);
+            } else {
+                // Shrink everything towards the best vertex
+                let best_point = simplex[0].0.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    let point: Vec<f64> = best_point.iter().zip(vertex.0.iter())
+                        .map(|(b, p)| b + 0.5 * (p - b))
+                        .collect();
+                    let value = f(&point);
+                    *vertex = (point, value);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    let (point, value) = simplex.swap_remove(0);
+    NelderMeadResult { point, value, iterations, converged }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimulationRng;
+
+    // Draws `count` returns from the model, started at its long-run variance
+    fn simulate(model: &ConditionalVarianceModel, seed: u64, count: usize) -> Vec<f64> {
+        let mut rng = SimulationRng::new(seed);
+        let mut variance = model.long_run_variance();
+        let mut returns = Vec::with_capacity(count);
+        for _ in 0..count {
+            let r = variance.sqrt() * rng.next_normal();
+            returns.push(r);
+            variance = model.next_variance(r, variance);
+        }
+        returns
+    }
+
+    fn fit(model: &ConditionalVarianceModel, seed: u64) -> ConditionalVarianceModel {
+        let returns = simulate(model, seed, 5000);
+        let sample_variance = returns.iter().map(|r| r * r).sum::<f64>() / returns.len() as f64;
+        let fit = fit_variance_model(&returns, &ConditionalVarianceModel::default_for(model.variant(), sample_variance))
+            .expect("window is long enough to fit");
+        assert!((fit.persistence - model.persistence()).abs() < 0.03,
+                "persistence {} vs {}", fit.persistence, model.persistence());
+        fit.params
+    }
+
+    #[test]
+    fn garch_fit_recovers_simulated_parameters() {
+        let truth = ConditionalVarianceModel::Garch(GarchParameters { omega: 2e-6, alpha: 0.08, beta: 0.9 });
+        match fit(&truth, 11) {
+            ConditionalVarianceModel::Garch(p) => {
+                assert!((p.alpha - 0.08).abs() < 0.03, "alpha {}", p.alpha);
+                assert!((p.beta - 0.9).abs() < 0.04, "beta {}", p.beta);
+            }
+            other => panic!("fit changed variant: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gjr_fit_recovers_the_leverage_term() {
+        let truth = ConditionalVarianceModel::GjrGarch(GjrGarchParameters {
+            omega: 2e-6,
+            alpha: 0.03,
+            gamma: 0.12,
+            beta: 0.88,
+        });
+        match fit(&truth, 13) {
+            ConditionalVarianceModel::GjrGarch(p) => {
+                assert!((p.gamma - 0.12).abs() < 0.05, "gamma {}", p.gamma);
+                assert!((p.beta - 0.88).abs() < 0.04, "beta {}", p.beta);
+                assert!(p.gamma > p.alpha, "down moves should carry the larger ARCH weight");
+            }
+            other => panic!("fit changed variant: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn egarch_fit_recovers_size_and_sign_effects() {
+        let truth = ConditionalVarianceModel::Egarch(EgarchParameters {
+            omega: -0.5,
+            alpha: 0.15,
+            gamma: -0.08,
+            beta: 0.96,
+        });
+        match fit(&truth, 17) {
+            ConditionalVarianceModel::Egarch(p) => {
+                assert!((p.alpha - 0.15).abs() < 0.05, "alpha {}", p.alpha);
+                assert!((p.gamma + 0.08).abs() < 0.04, "gamma {}", p.gamma);
+            }
+            other => panic!("fit changed variant: {:?}", other),
+        }
+    }
+}
@@ -36,6 +36,7 @@ struct NetworkMonitor {
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct OptimizationConfig {
     target_latency_percentile: f64,
     target_latency_threshold: f64,
@@ -117,6 +118,12 @@ impl NetworkMonitor {
     }
 }
 
+impl Default for LatencyEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LatencyEngine {
     pub fn new() -> Self {
         Self {
@@ -335,7 +342,7 @@ impl LatencyEngine {
         // Penalize packet loss
         score -= self.network_monitor.packet_loss_rate * 50.0;
         
-        score.max(0.0).min(100.0)
+        score.clamp(0.0, 100.0)
     }
 
     fn generate_optimization_recommendations(&self) -> Vec<String> {
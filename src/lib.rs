@@ -10,6 +10,16 @@ mod risk_manager;
 mod volatility;
 mod backtest;
 mod latency_engine;
+mod stress_test;
+mod audit_log;
+mod limit_hierarchy;
+mod margin;
+mod garch;
+mod regime;
+mod options;
+mod vol_surface;
+mod vol_registry;
+mod covariance;
 
 // Re-export all public items
 pub use order_book::*;
@@ -18,13 +28,30 @@ pub use risk_manager::*;
 pub use volatility::*;
 pub use backtest::*;
 pub use latency_engine::*;
+pub use stress_test::*;
+pub use audit_log::*;
+pub use limit_hierarchy::*;
+pub use margin::*;
+pub use garch::*;
+pub use regime::*;
+pub use options::*;
+pub use vol_surface::*;
+pub use vol_registry::*;
+pub use covariance::*;
 
-// Console logging macro
+// Console logging macro; falls back to stdout off wasm so native tests can run
+#[cfg(target_arch = "wasm32")]
 #[macro_export]
 macro_rules! console_log {
     ($($t:tt)*) => (web_sys::console::log_1(&format!($($t)*).into()))
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[macro_export]
+macro_rules! console_log {
+    ($($t:tt)*) => (println!($($t)*))
+}
+
 // Utility function to get current timestamp
 pub fn now() -> f64 {
     js_sys::Date::now()
@@ -46,6 +73,7 @@ pub struct MarketData {
     pub ask_price: f64,
     pub bid_size: f64,
     pub ask_size: f64,
+    // Traded since the previous tick for this symbol, not a cumulative total
     pub volume: f64,
 }
 
@@ -69,6 +97,10 @@ pub struct Order {
     pub price: f64,
     pub timestamp: f64,
     pub order_type: OrderType,
+    #[serde(default)]
+    pub account: String,
+    #[serde(default)]
+    pub strategy: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +124,86 @@ pub struct Position {
     pub average_price: f64,
     pub unrealized_pnl: f64,
     pub realized_pnl: f64,
+    #[serde(default)]
+    pub instrument: InstrumentType,
+    #[serde(default)]
+    pub greeks: Greeks,
+}
+
+impl Position {
+    pub fn new(symbol: &str, instrument: InstrumentType, greeks: Greeks) -> Self {
+        Position {
+            symbol: symbol.to_string(),
+            quantity: 0.0,
+            average_price: 0.0,
+            unrealized_pnl: 0.0,
+            realized_pnl: 0.0,
+            instrument,
+            greeks,
+        }
+    }
+
+    // Books a signed fill at `price`; returns the PnL realized by any closing
+    // quantity, before `fee`
+    pub fn apply_fill(&mut self, quantity_change: f64, price: f64, fee: f64) -> f64 {
+        let mut realized_pnl = 0.0;
+        if (self.quantity > 0.0 && quantity_change < 0.0) ||
+           (self.quantity < 0.0 && quantity_change > 0.0) {
+            let closing_quantity = quantity_change.abs().min(self.quantity.abs());
+            realized_pnl = (price - self.average_price) * closing_quantity * self.quantity.signum();
+        }
+
+        let new_quantity = self.quantity + quantity_change;
+        if new_quantity.abs() < 0.001 {
+            self.quantity = 0.0;
+            self.average_price = 0.0;
+        } else {
+            if new_quantity.signum() == quantity_change.signum() {
+                if self.quantity.signum() == quantity_change.signum() {
+                    // Adding to the position
+                    let total_cost = self.quantity * self.average_price + quantity_change * price;
+                    self.average_price = total_cost / new_quantity;
+                } else {
+                    // Flipped through flat; the remainder opens at the fill price
+                    self.average_price = price;
+                }
+            }
+            self.quantity = new_quantity;
+        }
+        self.realized_pnl += realized_pnl - fee;
+        realized_pnl
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum InstrumentType {
+    #[default]
+    Spot,
+    Future {
+        underlying: String,
+        expiry: f64,
+    },
+    Option {
+        underlying: String,
+        strike: f64,
+        expiry: f64,
+        option_type: OptionType,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OptionType {
+    Call,
+    Put,
+}
+
+// Sensitivities per unit of quantity; only options carry non-linear Greeks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,13 +219,21 @@ pub struct HFTEngine {
     order_book: OrderBook,
     market_maker: MarketMakerEngine,
     risk_manager: RiskManager,
-    volatility_model: VolatilityModel,
+    volatility_models: VolatilityRegistry, // By symbol
     backtest_engine: BacktestEngine,
     latency_engine: LatencyEngine,
+    stress_test_engine: StressTestEngine,
+    vol_surfaces: HashMap<String, VolatilitySurface>, // By underlying
     positions: HashMap<String, Position>,
     current_time: f64,
 }
 
+impl Default for HFTEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl HFTEngine {
     #[wasm_bindgen(constructor)]
@@ -124,9 +244,11 @@ impl HFTEngine {
             order_book: OrderBook::new(),
             market_maker: MarketMakerEngine::new(),
             risk_manager: RiskManager::new(),
-            volatility_model: VolatilityModel::new(),
+            volatility_models: VolatilityRegistry::new(),
             backtest_engine: BacktestEngine::new(),
             latency_engine: LatencyEngine::new(),
+            stress_test_engine: StressTestEngine::new(),
+            vol_surfaces: HashMap::new(),
             positions: HashMap::new(),
             current_time: 0.0,
         }
@@ -141,9 +263,20 @@ impl HFTEngine {
         
         // Update order book
         self.order_book.update(&market_data);
+        self.risk_manager.update_market_data(&market_data);
+        self.risk_manager.mark_to_market(&mut self.positions, market_data.timestamp);
         
-        // Update volatility model
-        let volatility = self.volatility_model.update(market_data.last_price, market_data.timestamp);
+        // Update this symbol's volatility model
+        let volatility_model = self.volatility_models.model_mut(&market_data.symbol);
+        let volatility = volatility_model.update(market_data.last_price, market_data.timestamp);
+        let volatility_estimate = volatility_model.get_volatility_estimate();
+        let volatility_regime = volatility_model.get_regime_state();
+        let jump_event = volatility_model.take_jump_event();
+        let jump_statistics = volatility_model.get_jump_statistics();
+        self.market_maker.set_regime_probabilities(&volatility_regime.probabilities);
+        if let Some(event) = &jump_event {
+            self.market_maker.on_jump(&market_data.symbol, event);
+        }
         
         // Generate quotes
         let quotes = self.market_maker.generate_quotes(
@@ -153,7 +286,14 @@ impl HFTEngine {
         );
         
         // Evaluate risk
-        let risk_metrics = self.risk_manager.evaluate_risk(&self.positions, &quotes);
+        // Trim quotes whose full fill would breach a limit, then evaluate the worst case
+        let quotes = self.risk_manager.trim_quotes(quotes, &self.positions);
+        let risk_metrics = self.risk_manager.evaluate_risk(&self.positions, &quotes, &self.order_book);
+
+        // Apply the automatic risk response ladder to outgoing quotes
+        let risk_response = self.risk_manager.update_risk_response(&risk_metrics, market_data.timestamp);
+        let quotes = self.risk_manager.apply_risk_response(quotes, &self.positions);
+        let flatten_orders = self.risk_manager.flatten_orders(&self.positions, market_data.timestamp);
         
         // Record latency
         let processing_time = now() - start_time;
@@ -162,7 +302,14 @@ impl HFTEngine {
         let response = ProcessingResult {
             quotes: quotes.clone(),
             risk_metrics,
+            risk_response,
+            flatten_orders,
             volatility,
+            volatility_estimate,
+            volatility_regime,
+            jump_event,
+            jump_statistics,
+            symbol_volatilities: self.volatility_models.volatilities(),
             order_book_stats: self.order_book.get_default_stats(),
             latency_stats: self.latency_engine.get_stats(),
         };
@@ -176,11 +323,12 @@ impl HFTEngine {
         
         let data: Vec<MarketData> = serde_wasm_bindgen::from_value(historical_data).unwrap();
         
+        // Simulated fills must never touch the live account, limits or models
         let results = self.backtest_engine.run_backtest(
             data,
-            &mut self.market_maker,
-            &mut self.risk_manager,
-            &mut self.volatility_model,
+            &mut self.market_maker.clone(),
+            &mut self.risk_manager.clone(),
+            &mut self.volatility_models.clone(),
         );
         
         serde_wasm_bindgen::to_value(&results).unwrap()
@@ -193,22 +341,365 @@ impl HFTEngine {
             current_positions: self.positions.len() as u32,
             total_pnl: self.positions.values().map(|p| p.realized_pnl + p.unrealized_pnl).sum(),
             latency_stats: self.latency_engine.get_stats(),
-            risk_metrics: RiskMetrics {
-                var_95: 0.0,
-                var_99: 0.0,
-                expected_shortfall: 0.0,
-                max_drawdown: 0.0,
-                gross_exposure: 0.0,
-                net_exposure: 0.0,
-                leverage: 0.0,
-                concentration_risk: 0.0,
-                risk_score: 0.0,
-            },
+            risk_metrics: RiskMetrics::default(),
         };
         
         serde_wasm_bindgen::to_value(&metrics).unwrap()
     }
 
+    // Runs the pre-trade checks on an outgoing order. Returns null when accepted,
+    // otherwise the rejection with its limit and attempted value.
+    #[wasm_bindgen]
+    pub fn validate_order(&mut self, order: JsValue) -> JsValue {
+        let order: Order = serde_wasm_bindgen::from_value(order).unwrap();
+        match self.risk_manager.validate_order(&order, &self.positions) {
+            Ok(()) => JsValue::NULL,
+            Err(rejection) => serde_wasm_bindgen::to_value(&rejection).unwrap(),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn get_risk_rejection_counts(&self) -> JsValue {
+        let counts = self.risk_manager.get_rejection_counts();
+        serde_wasm_bindgen::to_value(&counts).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_symbol_order_limits(&mut self, symbol: &str, max_order_quantity: f64, max_order_notional: f64) {
+        self.risk_manager.set_symbol_order_limits(symbol, max_order_quantity, max_order_notional);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_price_collar(&mut self, reference: JsValue, collar_pct: f64) {
+        let reference: CollarReference = serde_wasm_bindgen::from_value(reference).unwrap();
+        self.risk_manager.set_price_collar(reference, collar_pct);
+    }
+
+    // Books fees (negative) or rebates (positive) that settle outside fills
+    #[wasm_bindgen]
+    pub fn record_cash_pnl(&mut self, pnl_change: f64) {
+        self.risk_manager.update_daily_pnl(pnl_change);
+    }
+
+    // Counts a cancel or amend against the message throttle
+    #[wasm_bindgen]
+    pub fn record_message(&mut self, timestamp: f64) {
+        self.risk_manager.record_message(timestamp);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_throttle_limits(&mut self, max_orders_per_second: usize, max_messages_per_second: usize) {
+        self.risk_manager.set_throttle_limits(max_orders_per_second, max_messages_per_second);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_symbol_classification(&mut self, symbol: &str, classification: JsValue) {
+        let classification: SymbolClassification = serde_wasm_bindgen::from_value(classification).unwrap();
+        self.risk_manager.set_symbol_classification(symbol, classification);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_group_limit(&mut self, kind: JsValue, name: &str, max_gross: f64, max_net: f64) {
+        let kind: GroupKind = serde_wasm_bindgen::from_value(kind).unwrap();
+        self.risk_manager.set_group_limit(kind, name, GroupLimit { max_gross, max_net });
+    }
+
+    #[wasm_bindgen]
+    pub fn register_instrument(&mut self, symbol: &str, instrument: JsValue, greeks: JsValue) {
+        let instrument: InstrumentType = serde_wasm_bindgen::from_value(instrument).unwrap();
+        let greeks: Greeks = serde_wasm_bindgen::from_value(greeks).unwrap();
+        self.risk_manager.register_instrument(symbol, instrument, greeks);
+    }
+
+    #[wasm_bindgen]
+    pub fn engage_kill_switch(&mut self, reason: &str) {
+        self.risk_manager.engage_kill_switch(reason, self.current_time);
+    }
+
+    #[wasm_bindgen]
+    pub fn reset_kill_switch(&mut self) {
+        self.risk_manager.reset_kill_switch();
+    }
+
+    // Call when a flatten order is rejected or cancelled so its quantity is sent again
+    #[wasm_bindgen]
+    pub fn release_flatten(&mut self, symbol: &str) {
+        self.risk_manager.release_flatten(symbol);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_response_ladder(&mut self, steps: JsValue, spread_widen_factor: f64, size_reduction_factor: f64) {
+        let steps: Vec<ResponseStep> = serde_wasm_bindgen::from_value(steps).unwrap();
+        self.risk_manager.set_response_ladder(steps, spread_widen_factor, size_reduction_factor);
+    }
+
+    #[wasm_bindgen]
+    pub fn get_kill_switch_state(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.risk_manager.get_kill_switch()).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn query_risk_events(&self, start_time: f64, end_time: f64, event_type: JsValue) -> JsValue {
+        let event_type: Option<RiskEventType> = serde_wasm_bindgen::from_value(event_type).unwrap_or(None);
+        let events = self.risk_manager.query_audit_log(start_time, end_time, event_type);
+        serde_wasm_bindgen::to_value(&events).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn export_risk_events(&self, start_time: f64, end_time: f64, event_type: JsValue) -> String {
+        let event_type: Option<RiskEventType> = serde_wasm_bindgen::from_value(event_type).unwrap_or(None);
+        self.risk_manager.export_audit_log(start_time, end_time, event_type)
+    }
+
+    // Receives every audit entry as a JSON line, e.g. to append to IndexedDB or a server
+    #[wasm_bindgen]
+    pub fn set_risk_event_sink(&mut self, sink: Option<js_sys::Function>) {
+        self.risk_manager.set_audit_sink(sink);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_hierarchy_limits(&mut self, level: JsValue, account: &str, strategy: &str, symbol: &str, limits: JsValue) {
+        let level: LimitLevel = serde_wasm_bindgen::from_value(level).unwrap();
+        let limits: NodeLimits = serde_wasm_bindgen::from_value(limits).unwrap();
+        self.risk_manager.set_hierarchy_limits(level, account, strategy, symbol, limits);
+    }
+
+    #[wasm_bindgen]
+    pub fn record_fill(&mut self, order: JsValue, fee: f64) -> f64 {
+        let order: Order = serde_wasm_bindgen::from_value(order).unwrap();
+        let quantity_change = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+        self.market_maker.update_inventory(&order.symbol, quantity_change);
+        self.risk_manager.apply_fill(&mut self.positions, &order, fee)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_cash_balance(&mut self, cash: f64) {
+        self.risk_manager.set_cash_balance(cash);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_margin_rules(&mut self, rules: JsValue) {
+        let rules: MarginRules = serde_wasm_bindgen::from_value(rules).unwrap();
+        self.risk_manager.set_margin_rules(rules);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_unwind_impact(&mut self, impact: f64) {
+        self.order_book.set_beyond_depth_impact(impact);
+    }
+
+    // Returns an error message when the configuration is rejected
+    #[wasm_bindgen]
+    pub fn set_monte_carlo_config(&mut self, paths: usize, horizon_days: f64, distribution: JsValue,
+                                  seed: u64, refresh_interval_ms: f64) -> Option<String> {
+        let distribution: ReturnDistribution = serde_wasm_bindgen::from_value(distribution).unwrap();
+        self.risk_manager.set_monte_carlo_config(paths, horizon_days, distribution, seed, refresh_interval_ms).err()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_confidence_levels(&mut self, confidence_levels: Vec<f64>) {
+        self.risk_manager.set_confidence_levels(confidence_levels);
+    }
+
+    #[wasm_bindgen]
+    pub fn run_monte_carlo_var(&mut self) -> JsValue {
+        let result = self.risk_manager.refresh_monte_carlo_var(&self.positions);
+        serde_wasm_bindgen::to_value(&result).unwrap()
+    }
+
+    // Runs the GARCH, regime and covariance refits that have come due since the
+    // last call. Meant to be driven from a timer rather than the market data feed.
+    #[wasm_bindgen]
+    pub fn run_scheduled_refits(&mut self) -> JsValue {
+        let mut refitted = self.volatility_models.run_scheduled_refits();
+        refitted.sort();
+        let covariance_refits = self.risk_manager.refit_covariance_models();
+        serde_wasm_bindgen::to_value(&RefitSummary {
+            volatility_models: refitted,
+            covariance_models: covariance_refits,
+        }).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_covariance_config(&mut self, config: JsValue) {
+        let config: CovarianceConfig = serde_wasm_bindgen::from_value(config).unwrap();
+        self.risk_manager.set_covariance_config(config);
+    }
+
+    #[wasm_bindgen]
+    pub fn get_covariance_matrix(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.risk_manager.get_covariance_snapshot()).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_correlation_matrix(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.risk_manager.get_correlation_matrix()).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_hedge_ratio(&self, symbol: &str, hedge_symbol: &str) -> Option<f64> {
+        self.risk_manager.minimum_variance_hedge_ratio(symbol, hedge_symbol)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_volatility_parameters(&mut self, symbol: &str, parameters: JsValue) {
+        let parameters: VolatilityParameters = serde_wasm_bindgen::from_value(parameters).unwrap();
+        self.volatility_models.set_parameters(symbol, parameters);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_default_volatility_parameters(&mut self, parameters: JsValue) {
+        let parameters: VolatilityParameters = serde_wasm_bindgen::from_value(parameters).unwrap();
+        self.volatility_models.set_default_parameters(parameters);
+    }
+
+    #[wasm_bindgen]
+    pub fn get_volatility_parameters(&self, symbol: &str) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.volatility_models.parameters(symbol)).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_symbol_volatilities(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.volatility_models.volatilities()).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_garch_diagnostics(&self, symbol: &str) -> JsValue {
+        let fit = self.volatility_models.get(symbol).and_then(|model| model.get_garch_fit());
+        serde_wasm_bindgen::to_value(&fit).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn refit_garch(&mut self, symbol: &str) -> JsValue {
+        let fit = self.volatility_models.model_mut(symbol).refit_garch();
+        serde_wasm_bindgen::to_value(&fit).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_garch_refit_schedule(&mut self, symbol: &str, refit_interval: usize, fit_window: usize) {
+        self.volatility_models.update_parameters(symbol, |parameters| {
+            parameters.refit_interval = refit_interval;
+            parameters.fit_window = fit_window;
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn set_variance_model(&mut self, symbol: &str, variant: JsValue) {
+        let variant: GarchVariant = serde_wasm_bindgen::from_value(variant).unwrap();
+        self.volatility_models.update_parameters(symbol, |parameters| parameters.variance_model = variant);
+    }
+
+    #[wasm_bindgen]
+    pub fn compare_variance_models(&self, symbol: &str) -> JsValue {
+        let comparisons = self.volatility_models.get(symbol)
+            .map(|model| model.compare_variance_models())
+            .unwrap_or_default();
+        serde_wasm_bindgen::to_value(&comparisons).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_volatility_estimators(&mut self, symbol: &str, weights: JsValue) {
+        let weights: Vec<EstimatorWeight> = serde_wasm_bindgen::from_value(weights).unwrap();
+        self.volatility_models.update_parameters(symbol, |parameters| parameters.estimator_weights = weights);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_volatility_bar_interval(&mut self, symbol: &str, interval_secs: f64, range_window: usize) {
+        self.volatility_models.update_parameters(symbol, |parameters| {
+            parameters.bar_interval_secs = interval_secs;
+            parameters.range_window = range_window;
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn set_regime_states(&mut self, symbol: &str, num_states: usize) {
+        self.volatility_models.update_parameters(symbol, |parameters| parameters.regime_states = num_states);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_regime_adjustments(&mut self, spread_multipliers: Vec<f64>, size_multipliers: Vec<f64>) {
+        self.market_maker.set_regime_adjustments(spread_multipliers, size_multipliers);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_jump_pull_duration(&mut self, duration_ms: f64) {
+        self.market_maker.set_jump_pull_duration(duration_ms);
+    }
+
+    // Returns the option's analytics, or null when no implied volatility exists
+    #[wasm_bindgen]
+    pub fn process_option_quote(&mut self, quote: JsValue) -> JsValue {
+        let quote: OptionQuote = serde_wasm_bindgen::from_value(quote).unwrap();
+
+        match analyze_option_quote(&quote, self.volatility_models.model_mut(&quote.underlying)) {
+            Ok(analytics) => {
+                // Risk Greeks are per 1% of volatility and per calendar day
+                let greeks = &analytics.greeks;
+                self.risk_manager.register_instrument(&quote.symbol, quote.instrument(), Greeks {
+                    delta: greeks.delta,
+                    gamma: greeks.gamma,
+                    vega: greeks.vega / 100.0,
+                    theta: greeks.theta / 365.0,
+                });
+                self.vol_surfaces.entry(quote.underlying.clone())
+                    .or_insert_with(|| VolatilitySurface::new(&quote.underlying))
+                    .add_quote(&quote, analytics.implied_volatility);
+                serde_wasm_bindgen::to_value(&analytics).unwrap()
+            }
+            Err(error) => {
+                console_log!("No implied volatility for {}: {}", quote.symbol, error);
+                JsValue::NULL
+            }
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn fit_vol_surface(&mut self, underlying: &str) -> JsValue {
+        let slices = self.vol_surfaces.get_mut(underlying)
+            .map(|surface| surface.fit())
+            .unwrap_or_default();
+        serde_wasm_bindgen::to_value(&slices).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_surface_volatility(&self, underlying: &str, strike: f64, expiry: f64) -> Option<f64> {
+        self.vol_surfaces.get(underlying)?.implied_volatility(strike, expiry)
+    }
+
+    #[wasm_bindgen]
+    pub fn check_surface_arbitrage(&self, underlying: &str) -> JsValue {
+        let violations = self.vol_surfaces.get(underlying)
+            .map(|surface| surface.check_arbitrage())
+            .unwrap_or_default();
+        serde_wasm_bindgen::to_value(&violations).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_trading_day_rollover(&mut self, offset_hours: f64) {
+        self.risk_manager.set_trading_day_rollover(offset_hours);
+    }
+
+    #[wasm_bindgen]
+    pub fn load_stress_history(&mut self, historical_data: JsValue) {
+        let data: Vec<MarketData> = serde_wasm_bindgen::from_value(historical_data).unwrap();
+        self.stress_test_engine.load_historical_data(data);
+    }
+
+    #[wasm_bindgen]
+    pub fn add_stress_scenario(&mut self, scenario: JsValue) {
+        let scenario: StressScenario = serde_wasm_bindgen::from_value(scenario).unwrap();
+        self.stress_test_engine.add_scenario(scenario);
+    }
+
+    #[wasm_bindgen]
+    pub fn run_stress_tests(&self) -> JsValue {
+        let results = self.stress_test_engine.run_all(&self.positions, &self.risk_manager);
+        serde_wasm_bindgen::to_value(&results).unwrap()
+    }
+
     #[wasm_bindgen]
     pub fn benchmark_performance(&mut self) -> JsValue {
         let benchmark = self.latency_engine.benchmark_processing_pipeline();
@@ -230,11 +721,24 @@ impl HFTEngine {
 pub struct ProcessingResult {
     pub quotes: Vec<Quote>,
     pub risk_metrics: RiskMetrics,
+    pub risk_response: RiskResponse,
+    pub flatten_orders: Vec<Order>,
     pub volatility: f64,
+    pub volatility_estimate: VolatilityEstimate,
+    pub volatility_regime: RegimeState,
+    pub jump_event: Option<JumpEvent>,
+    pub jump_statistics: JumpStatistics,
+    pub symbol_volatilities: HashMap<String, f64>, // Annualized, by symbol
     pub order_book_stats: OrderBookStats,
     pub latency_stats: LatencyStats,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefitSummary {
+    pub volatility_models: Vec<String>, // Symbols whose GARCH or regime model was refit
+    pub covariance_models: usize,       // Per-symbol GARCH fits behind the DCC correlation
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceMetrics {
     pub total_trades: u32,
@@ -0,0 +1,317 @@
+use crate::*;
+use std::collections::HashMap;
+
+// Firm -> account -> strategy -> symbol limit tree. Nodes are created on first
+// use and each node's limits can be changed independently of the others.
+// Fills are booked per account and strategy; any part of an engine position
+// that no fill accounts for sits in the default book.
+#[derive(Debug, Clone)]
+pub struct LimitHierarchy {
+    nodes: HashMap<String, LimitNode>,
+    books: HashMap<String, HashMap<(String, String), f64>>, // Symbol -> (account, strategy) -> quantity
+}
+
+#[derive(Debug, Clone)]
+struct LimitNode {
+    level: LimitLevel,
+    limits: NodeLimits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LimitLevel {
+    Firm,
+    Account,
+    Strategy,
+    Symbol,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeLimits {
+    #[serde(default = "unlimited")]
+    pub max_gross_notional: f64,
+    #[serde(default = "unlimited")]
+    pub max_net_notional: f64,
+    #[serde(default = "unlimited")]
+    pub max_order_notional: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeUtilization {
+    pub node: String,
+    pub level: Option<LimitLevel>,
+    pub gross_exposure: f64,
+    pub net_exposure: f64,
+    pub gross_limit: f64,
+    pub net_limit: f64,
+    pub utilization: f64,
+}
+
+// Node limit breached by an order, with the node path
+#[derive(Debug, Clone)]
+pub struct HierarchyBreach {
+    pub node: String,
+    pub kind: HierarchyLimitKind,
+    pub limit: f64,
+    pub attempted: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HierarchyLimitKind {
+    Gross,
+    Net,
+    OrderNotional,
+}
+
+fn unlimited() -> f64 {
+    f64::INFINITY
+}
+
+impl Default for NodeLimits {
+    fn default() -> Self {
+        Self {
+            max_gross_notional: f64::INFINITY,
+            max_net_notional: f64::INFINITY,
+            max_order_notional: f64::INFINITY,
+        }
+    }
+}
+
+impl LimitHierarchy {
+    pub fn new(firm_limits: NodeLimits) -> Self {
+        let mut nodes = HashMap::new();
+        nodes.insert("firm".to_string(), LimitNode {
+            level: LimitLevel::Firm,
+            limits: firm_limits,
+        });
+        Self { nodes, books: HashMap::new() }
+    }
+
+    // Node paths from the firm down to the order's symbol. Untagged orders book
+    // to the "default" account and strategy.
+    pub fn order_path(order: &Order) -> Vec<(String, LimitLevel)> {
+        let (account, strategy) = Self::order_book(order);
+        Self::book_path(account, strategy, &order.symbol)
+    }
+
+    fn order_book(order: &Order) -> (&str, &str) {
+        let account = if order.account.is_empty() { "default" } else { &order.account };
+        let strategy = if order.strategy.is_empty() { "default" } else { &order.strategy };
+        (account, strategy)
+    }
+
+    fn book_path(account: &str, strategy: &str, symbol: &str) -> Vec<(String, LimitLevel)> {
+        vec![
+            ("firm".to_string(), LimitLevel::Firm),
+            (format!("firm/{}", account), LimitLevel::Account),
+            (format!("firm/{}/{}", account, strategy), LimitLevel::Strategy),
+            (format!("firm/{}/{}/{}", account, strategy, symbol), LimitLevel::Symbol),
+        ]
+    }
+
+    // Part of `symbol`'s engine position held under the node at `path`
+    fn node_quantity(&self, path: &str, symbol: &str, position_quantity: f64) -> f64 {
+        let under_node = |account: &str, strategy: &str| {
+            Self::book_path(account, strategy, symbol).iter().any(|(node, _)| node == path)
+        };
+        let booked = self.books.get(symbol);
+        let booked_total: f64 = booked.map_or(0.0, |books| books.values().sum());
+
+        let mut quantity: f64 = booked.into_iter().flatten()
+            .filter(|((account, strategy), _)| under_node(account, strategy))
+            .map(|(_, quantity)| quantity)
+            .sum();
+        if under_node("default", "default") {
+            quantity += position_quantity - booked_total;
+        }
+        quantity
+    }
+
+    pub fn node_path(level: LimitLevel, account: &str, strategy: &str, symbol: &str) -> String {
+        match level {
+            LimitLevel::Firm => "firm".to_string(),
+            LimitLevel::Account => format!("firm/{}", account),
+            LimitLevel::Strategy => format!("firm/{}/{}", account, strategy),
+            LimitLevel::Symbol => format!("firm/{}/{}/{}", account, strategy, symbol),
+        }
+    }
+
+    fn node_mut(&mut self, path: &str, level: LimitLevel) -> &mut LimitNode {
+        self.nodes.entry(path.to_string()).or_insert_with(|| LimitNode {
+            level,
+            limits: NodeLimits::default(),
+        })
+    }
+
+    pub fn set_limits(&mut self, path: &str, level: LimitLevel, limits: NodeLimits) {
+        self.node_mut(path, level).limits = limits;
+    }
+
+    // Books under the node net per symbol before gross is taken
+    fn node_exposures(&self, path: &str, positions: &HashMap<String, Position>, mark: &dyn Fn(&str) -> f64) -> (f64, f64) {
+        positions.values()
+            .map(|pos| self.node_quantity(path, &pos.symbol, pos.quantity) * mark(&pos.symbol))
+            .fold((0.0, 0.0), |(gross, net), exposure| (gross + exposure.abs(), net + exposure))
+    }
+
+    // First limit the order would breach at any level of its path
+    pub fn check_order(&self, order: &Order, positions: &HashMap<String, Position>, mark: &dyn Fn(&str) -> f64) -> Option<HierarchyBreach> {
+        let order_notional = order.quantity * order.price;
+        let signed_quantity = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+
+        for (path, _) in Self::order_path(order) {
+            let node = match self.nodes.get(&path) {
+                Some(node) => node,
+                None => continue,
+            };
+            let limits = &node.limits;
+
+            if order_notional > limits.max_order_notional {
+                return Some(HierarchyBreach {
+                    node: path,
+                    kind: HierarchyLimitKind::OrderNotional,
+                    limit: limits.max_order_notional,
+                    attempted: order_notional,
+                });
+            }
+
+            let (gross, net) = self.node_exposures(&path, positions, mark);
+            let position_quantity = positions.get(&order.symbol).map_or(0.0, |pos| pos.quantity);
+            let current_quantity = self.node_quantity(&path, &order.symbol, position_quantity);
+            let symbol_mark = mark(&order.symbol);
+            let old_exposure = current_quantity * symbol_mark;
+            let new_exposure = (current_quantity + signed_quantity) * symbol_mark;
+            let new_gross = gross - old_exposure.abs() + new_exposure.abs();
+            let new_net = net - old_exposure + new_exposure;
+
+            if new_gross > limits.max_gross_notional && new_gross > gross {
+                return Some(HierarchyBreach {
+                    node: path,
+                    kind: HierarchyLimitKind::Gross,
+                    limit: limits.max_gross_notional,
+                    attempted: new_gross,
+                });
+            }
+
+            if new_net.abs() > limits.max_net_notional && new_net.abs() > net.abs() {
+                return Some(HierarchyBreach {
+                    node: path,
+                    kind: HierarchyLimitKind::Net,
+                    limit: limits.max_net_notional,
+                    attempted: new_net.abs(),
+                });
+            }
+        }
+
+        None
+    }
+
+    // Books the fill's signed quantity to its account and strategy
+    pub fn book_fill(&mut self, order: &Order) {
+        let signed_quantity = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+        let (account, strategy) = Self::order_book(order);
+        *self.books.entry(order.symbol.clone()).or_default()
+            .entry((account.to_string(), strategy.to_string()))
+            .or_insert(0.0) += signed_quantity;
+        for (path, level) in Self::order_path(order) {
+            self.node_mut(&path, level);
+        }
+    }
+
+    pub fn utilization(&self, positions: &HashMap<String, Position>, mark: &dyn Fn(&str) -> f64) -> Vec<NodeUtilization> {
+        let mut nodes: Vec<NodeUtilization> = self.nodes.iter()
+            .map(|(path, node)| {
+                let (gross_exposure, net_exposure) = self.node_exposures(path, positions, mark);
+                let limits = &node.limits;
+                NodeUtilization {
+                    node: path.clone(),
+                    level: Some(node.level),
+                    gross_exposure,
+                    net_exposure,
+                    gross_limit: limits.max_gross_notional,
+                    net_limit: limits.max_net_notional,
+                    utilization: (gross_exposure / limits.max_gross_notional)
+                        .max(net_exposure.abs() / limits.max_net_notional),
+                }
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.node.cmp(&b.node));
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(strategy: &str, side: OrderSide, quantity: f64) -> Order {
+        Order {
+            id: format!("{}-{}", strategy, quantity),
+            symbol: "XYZ".to_string(),
+            side,
+            quantity,
+            price: 10.0,
+            timestamp: 0.0,
+            order_type: OrderType::Limit,
+            account: "acct".to_string(),
+            strategy: strategy.to_string(),
+        }
+    }
+
+    fn position(quantity: f64) -> HashMap<String, Position> {
+        let mut position = Position::new("XYZ", InstrumentType::Spot, Greeks::default());
+        position.quantity = quantity;
+        HashMap::from([("XYZ".to_string(), position)])
+    }
+
+    fn node_gross(hierarchy: &LimitHierarchy, positions: &HashMap<String, Position>, node: &str) -> f64 {
+        hierarchy.utilization(positions, &|_| 10.0).iter()
+            .find(|utilization| utilization.node == node)
+            .map_or(0.0, |utilization| utilization.gross_exposure)
+    }
+
+    #[test]
+    fn fills_stay_with_the_strategy_that_made_them() {
+        let mut hierarchy = LimitHierarchy::new(NodeLimits::default());
+        hierarchy.book_fill(&order("a", OrderSide::Buy, 1000.0));
+        hierarchy.book_fill(&order("b", OrderSide::Buy, 1.0));
+        let positions = position(1001.0);
+
+        assert_eq!(node_gross(&hierarchy, &positions, "firm/acct/a"), 10_000.0);
+        assert_eq!(node_gross(&hierarchy, &positions, "firm/acct/b"), 10.0);
+        assert_eq!(node_gross(&hierarchy, &positions, "firm/acct"), 10_010.0);
+        assert_eq!(node_gross(&hierarchy, &positions, "firm"), 10_010.0);
+    }
+
+    #[test]
+    fn orders_are_checked_against_their_own_book() {
+        let mut hierarchy = LimitHierarchy::new(NodeLimits::default());
+        hierarchy.book_fill(&order("a", OrderSide::Buy, 1000.0));
+        hierarchy.book_fill(&order("b", OrderSide::Buy, 1.0));
+        let limits = NodeLimits { max_gross_notional: 5_000.0, ..NodeLimits::default() };
+        hierarchy.set_limits("firm/acct/a", LimitLevel::Strategy, limits);
+        let positions = position(1001.0);
+
+        // Strategy a is over its limit, so it may only reduce
+        assert!(hierarchy.check_order(&order("a", OrderSide::Sell, 100.0), &positions, &|_| 10.0).is_none());
+        let breach = hierarchy.check_order(&order("a", OrderSide::Buy, 1.0), &positions, &|_| 10.0).unwrap();
+        assert_eq!(breach.node, "firm/acct/a");
+        assert_eq!(breach.attempted, 10_010.0);
+    }
+
+    #[test]
+    fn unbooked_quantity_sits_in_the_default_book() {
+        let mut hierarchy = LimitHierarchy::new(NodeLimits::default());
+        hierarchy.book_fill(&order("a", OrderSide::Buy, 100.0));
+        let positions = position(150.0);
+
+        assert_eq!(node_gross(&hierarchy, &positions, "firm/acct/a"), 1_000.0);
+        let default = hierarchy.node_exposures("firm/default/default", &positions, &|_| 10.0);
+        assert_eq!(default, (500.0, 500.0));
+    }
+}
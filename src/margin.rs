@@ -0,0 +1,95 @@
+use crate::*;
+
+// Cash ledger plus the margin rules applied per instrument type. Margin is
+// charged as a fraction of notional; short options are margined on the
+// underlying and long options must be paid for in full.
+#[derive(Debug, Clone)]
+pub struct MarginAccount {
+    pub cash: f64,
+    rules: MarginRules,
+    margin_call: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginRules {
+    pub spot: MarginRule,
+    pub future: MarginRule,
+    pub long_option: MarginRule,
+    pub short_option: MarginRule,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MarginRule {
+    pub initial: f64,
+    pub maintenance: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarginStatus {
+    pub cash: f64,
+    pub equity: f64,
+    pub initial_margin: f64,
+    pub maintenance_margin: f64,
+    pub buying_power: f64,
+    pub margin_call: bool,
+}
+
+impl MarginAccount {
+    pub fn new(cash: f64) -> Self {
+        Self {
+            cash,
+            rules: MarginRules {
+                spot: MarginRule { initial: 0.5, maintenance: 0.25 },
+                future: MarginRule { initial: 0.1, maintenance: 0.08 },
+                long_option: MarginRule { initial: 1.0, maintenance: 1.0 },
+                short_option: MarginRule { initial: 0.2, maintenance: 0.15 },
+            },
+            margin_call: false,
+        }
+    }
+
+    pub fn set_rules(&mut self, rules: MarginRules) {
+        self.rules = rules;
+    }
+
+    // Initial and maintenance requirement for a holding. `mark` prices the
+    // instrument itself, `underlying_price` its underlying.
+    pub fn requirement(&self, instrument: &InstrumentType, quantity: f64, mark: f64, underlying_price: f64) -> (f64, f64) {
+        let (rule, notional) = match instrument {
+            InstrumentType::Spot => (self.rules.spot, quantity.abs() * mark),
+            InstrumentType::Future { .. } => (self.rules.future, quantity.abs() * mark),
+            InstrumentType::Option { .. } if quantity >= 0.0 => (self.rules.long_option, quantity * mark),
+            InstrumentType::Option { .. } => (
+                self.rules.short_option,
+                -quantity * underlying_price,
+            ),
+        };
+
+        (notional * rule.initial, notional * rule.maintenance)
+    }
+
+    // Cash moves by the traded notional; fees are booked separately
+    pub fn apply_fill(&mut self, order: &Order) {
+        let notional = order.quantity * order.price;
+        match order.side {
+            OrderSide::Buy => self.cash -= notional,
+            OrderSide::Sell => self.cash += notional,
+        }
+    }
+
+    pub fn adjust_cash(&mut self, amount: f64) {
+        self.cash += amount;
+    }
+
+    // Returns true when the account has just gone into a margin call
+    pub fn update_margin_call(&mut self, equity: f64, maintenance_margin: f64) -> bool {
+        let in_call = maintenance_margin > 0.0 && equity < maintenance_margin;
+        let entered = in_call && !self.margin_call;
+        self.margin_call = in_call;
+        entered
+    }
+
+    pub fn is_margin_call(&self) -> bool {
+        self.margin_call
+    }
+}
@@ -18,6 +18,10 @@ struct MarketMakerConfig {
     max_inventory_deviation: f64,
     skew_factor: f64,
     volatility_adjustment_factor: f64,
+    quote_horizon_secs: f64, // Horizon over which quotes are exposed to price moves
+    regime_spread_multipliers: Vec<f64>, // Per volatility regime, calmest first
+    regime_size_multipliers: Vec<f64>,
+    jump_pull_duration_ms: f64, // How long quotes stay pulled after a price jump
     tick_size: f64,
 }
 
@@ -27,10 +31,13 @@ struct MarketMakerState {
     quote_history: Vec<Quote>,
     last_update_time: f64,
     current_volatility: f64,
+    regime_probabilities: Vec<f64>,
+    quotes_pulled_until: HashMap<String, f64>, // Symbol -> end of its post-jump pull
     pnl_tracker: PnlTracker,
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct InventorySkewEngine {
     max_position_size: f64,
     skew_intensity: f64,
@@ -54,6 +61,7 @@ pub struct PnlTracker {
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct FillEvent {
     timestamp: f64,
     side: OrderSide,
@@ -62,6 +70,12 @@ struct FillEvent {
     was_adverse: bool,
 }
 
+impl Default for MarketMakerEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MarketMakerEngine {
     pub fn new() -> Self {
         Self {
@@ -73,6 +87,10 @@ impl MarketMakerEngine {
                 max_inventory_deviation: 1000.0,
                 skew_factor: 0.5,
                 volatility_adjustment_factor: 2.0,
+                quote_horizon_secs: 10.0,
+                regime_spread_multipliers: vec![1.0, 1.8],
+                regime_size_multipliers: vec![1.0, 0.5],
+                jump_pull_duration_ms: 2000.0,
                 tick_size: 0.01,
             },
             state: MarketMakerState {
@@ -80,6 +98,8 @@ impl MarketMakerEngine {
                 quote_history: Vec::new(),
                 last_update_time: 0.0,
                 current_volatility: 0.0,
+                regime_probabilities: Vec::new(),
+                quotes_pulled_until: HashMap::new(),
                 pnl_tracker: PnlTracker {
                     realized_pnl: 0.0,
                     unrealized_pnl: 0.0,
@@ -112,6 +132,11 @@ impl MarketMakerEngine {
 
         let symbol = &market_data.symbol;
 
+        // Stay out of this symbol's market briefly after a jump in it
+        if self.state.quotes_pulled_until.get(symbol).is_some_and(|&until| market_data.timestamp < until) {
+            return Vec::new();
+        }
+
         // Calculate base spread
         let base_spread = self.calculate_base_spread(volatility, order_book);
 
@@ -164,16 +189,57 @@ impl MarketMakerEngine {
         // Start with target spread
         let mut spread = self.config.target_spread_bps;
 
-        // Adjust for volatility
-        spread += volatility * self.config.volatility_adjustment_factor * 10000.0;
+        // Adjust for the price move expected over the quote horizon
+        let horizon_years = self.config.quote_horizon_secs / (TRADING_DAYS_PER_YEAR * TRADING_SECONDS_PER_DAY);
+        let horizon_volatility = volatility * horizon_years.sqrt();
+        spread += horizon_volatility * self.config.volatility_adjustment_factor * 10000.0;
 
         // Adjust for order book conditions
         spread += self.calculate_order_book_adjustment(order_book);
 
+        // Scale by the probability-weighted regime multiplier
+        spread *= self.regime_multiplier(&self.config.regime_spread_multipliers);
+
         // Clamp to min/max bounds
         spread.max(self.config.min_spread_bps).min(self.config.max_spread_bps)
     }
 
+    // Expected multiplier under the filtered regime probabilities. Regimes
+    // beyond the configured list use its last entry.
+    fn regime_multiplier(&self, multipliers: &[f64]) -> f64 {
+        let probabilities = &self.state.regime_probabilities;
+        if probabilities.is_empty() || multipliers.is_empty() {
+            return 1.0;
+        }
+
+        probabilities.iter()
+            .enumerate()
+            .map(|(regime, probability)| {
+                probability * multipliers.get(regime).or(multipliers.last()).copied().unwrap_or(1.0)
+            })
+            .sum()
+    }
+
+    // Pulls the symbol's quotes for a short window after a price jump
+    pub fn on_jump(&mut self, symbol: &str, event: &JumpEvent) {
+        let pulled_until = event.timestamp + self.config.jump_pull_duration_ms;
+        let until = self.state.quotes_pulled_until.entry(symbol.to_string()).or_insert(0.0);
+        *until = until.max(pulled_until);
+    }
+
+    pub fn set_jump_pull_duration(&mut self, duration_ms: f64) {
+        self.config.jump_pull_duration_ms = duration_ms.max(0.0);
+    }
+
+    pub fn set_regime_probabilities(&mut self, probabilities: &[f64]) {
+        self.state.regime_probabilities = probabilities.to_vec();
+    }
+
+    pub fn set_regime_adjustments(&mut self, spread_multipliers: Vec<f64>, size_multipliers: Vec<f64>) {
+        self.config.regime_spread_multipliers = spread_multipliers;
+        self.config.regime_size_multipliers = size_multipliers;
+    }
+
     fn calculate_order_book_adjustment(&self, order_book: &OrderBook) -> f64 {
         let stats = order_book.get_default_stats();
 
@@ -207,7 +273,9 @@ impl MarketMakerEngine {
         // Adjust sizes based on volatility (smaller sizes in high volatility)
         let volatility_factor = (1.0 / (1.0 + volatility * 5.0)).max(0.3);
 
-        let adjusted_size = base_size * inventory_factor * volatility_factor;
+        let regime_factor = self.regime_multiplier(&self.config.regime_size_multipliers);
+
+        let adjusted_size = base_size * inventory_factor * volatility_factor * regime_factor;
 
         // Skew sizes based on inventory (quote smaller on the side we're long)
         let bid_size = if current_inventory > 0.0 {
@@ -266,7 +334,7 @@ impl MarketMakerEngine {
         // Reduce confidence when order book is imbalanced
         confidence -= stats.imbalance.abs() * 0.1;
 
-        confidence.max(0.1).min(1.0)
+        confidence.clamp(0.1, 1.0)
     }
 
     fn round_to_tick(&self, price: f64) -> f64 {
@@ -303,12 +371,13 @@ impl MarketMakerEngine {
         self.adverse_selection_detector.recent_fills.remove(0);
     }
 
-    // Calculate PnL for this trade and update the total
+    // Edge captured against the market price at fill; it stays unrealized
+    // until the inventory is closed out
     let pnl_change = match order.side {
         OrderSide::Buy => (market_price - order.price) * order.quantity,
         OrderSide::Sell => (order.price - market_price) * order.quantity,
     };
-    self.state.pnl_tracker.total_pnl += pnl_change;
+    self.state.pnl_tracker.unrealized_pnl += pnl_change;
 
     // Update other PnL tracking metrics
     self.state.pnl_tracker.trade_count += 1;
@@ -0,0 +1,495 @@
+use crate::*;
+use std::fmt;
+
+const MIN_IMPLIED_VOLATILITY: f64 = 1e-6;
+const MAX_IMPLIED_VOLATILITY: f64 = 10.0;
+const MILLIS_PER_YEAR: f64 = 365.0 * 86_400_000.0;
+
+// Black-Scholes prices options on spot with a continuous dividend yield;
+// Black-76 prices options on a forward or future
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PricingModel {
+    #[default]
+    BlackScholes,
+    Black76,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionInputs {
+    pub model: PricingModel,
+    pub option_type: OptionType,
+    pub underlying_price: f64, // Spot for Black-Scholes, forward for Black-76
+    pub strike: f64,
+    pub time_to_expiry: f64,   // Years
+    pub rate: f64,
+    pub dividend_yield: f64,   // Ignored by Black-76
+    pub volatility: f64,
+}
+
+// Greeks per unit of the option. Vega, vanna and volga are per 1.00 of
+// volatility; theta and charm are per year of calendar time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionGreeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
+    pub rho: f64,
+    pub vanna: f64,
+    pub volga: f64,
+    pub charm: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImpliedVolError {
+    Expired,
+    // Price outside the no-arbitrage bounds for any volatility
+    ArbitrageBound { price: f64, lower: f64, upper: f64 },
+    // Price equal to intrinsic value carries no volatility information
+    NoTimeValue { price: f64 },
+    NoConvergence { price: f64 },
+}
+
+impl fmt::Display for ImpliedVolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImpliedVolError::Expired => write!(f, "option has expired"),
+            ImpliedVolError::ArbitrageBound { price, lower, upper } =>
+                write!(f, "price {:.6} outside arbitrage bounds [{:.6}, {:.6}]", price, lower, upper),
+            ImpliedVolError::NoTimeValue { price } => write!(f, "price {:.6} has no time value", price),
+            ImpliedVolError::NoConvergence { price } => write!(f, "implied volatility did not converge for price {:.6}", price),
+        }
+    }
+}
+
+// Option market data as it arrives from the feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionQuote {
+    pub symbol: String,
+    pub underlying: String,
+    pub strike: f64,
+    pub expiry: f64,    // Epoch milliseconds
+    pub option_type: OptionType,
+    pub bid: f64,
+    pub ask: f64,
+    pub underlying_price: f64,
+    pub timestamp: f64,
+    #[serde(default)]
+    pub model: PricingModel,
+    #[serde(default)]
+    pub rate: f64,
+    #[serde(default)]
+    pub dividend_yield: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionAnalytics {
+    pub symbol: String,
+    pub implied_volatility: f64,
+    pub greeks: OptionGreeks,
+    pub realized_volatility: f64,
+    pub garch_forecast: f64,
+    pub volatility_premium: f64, // Implied minus realized
+}
+
+impl OptionInputs {
+    // Cost of carry: r - q on spot, zero on a forward
+    fn carry(&self) -> f64 {
+        match self.model {
+            PricingModel::BlackScholes => self.rate - self.dividend_yield,
+            PricingModel::Black76 => 0.0,
+        }
+    }
+
+    fn is_call(&self) -> bool {
+        matches!(self.option_type, OptionType::Call)
+    }
+
+    // Discounted underlying and strike: S e^((b-r)T) and K e^(-rT)
+    fn discounted_legs(&self) -> (f64, f64) {
+        let t = self.time_to_expiry;
+        (
+            self.underlying_price * ((self.carry() - self.rate) * t).exp(),
+            self.strike * (-self.rate * t).exp(),
+        )
+    }
+
+    fn d1_d2(&self) -> (f64, f64) {
+        let t = self.time_to_expiry;
+        let vol_sqrt_t = self.volatility * t.sqrt();
+        let d1 = ((self.underlying_price / self.strike).ln() + (self.carry() + 0.5 * self.volatility * self.volatility) * t)
+            / vol_sqrt_t;
+        (d1, d1 - vol_sqrt_t)
+    }
+
+    // No-arbitrage price range over all volatilities
+    pub fn price_bounds(&self) -> (f64, f64) {
+        let (underlying, strike) = self.discounted_legs();
+        if self.is_call() {
+            ((underlying - strike).max(0.0), underlying)
+        } else {
+            ((strike - underlying).max(0.0), strike)
+        }
+    }
+
+    fn with_volatility(&self, volatility: f64) -> Self {
+        Self { volatility, ..self.clone() }
+    }
+}
+
+pub fn option_price(inputs: &OptionInputs) -> f64 {
+    if inputs.time_to_expiry <= 0.0 || inputs.volatility <= 0.0 {
+        // Intrinsic value on the discounted legs
+        return inputs.price_bounds().0;
+    }
+
+    let (underlying, strike) = inputs.discounted_legs();
+    let (d1, d2) = inputs.d1_d2();
+    if inputs.is_call() {
+        underlying * normal_cdf(d1) - strike * normal_cdf(d2)
+    } else {
+        strike * normal_cdf(-d2) - underlying * normal_cdf(-d1)
+    }
+}
+
+// Generalized Black-Scholes-Merton Greeks (Haug), covering both models through the cost of carry
+pub fn option_greeks(inputs: &OptionInputs) -> OptionGreeks {
+    let t = inputs.time_to_expiry;
+    if t <= 0.0 || inputs.volatility <= 0.0 {
+        return OptionGreeks::default();
+    }
+
+    let sigma = inputs.volatility;
+    let b = inputs.carry();
+    let r = inputs.rate;
+    let sqrt_t = t.sqrt();
+    let (underlying, strike) = inputs.discounted_legs();
+    let carry_discount = ((b - r) * t).exp();
+    let (d1, d2) = inputs.d1_d2();
+    let density = normal_pdf(d1);
+
+    let gamma = carry_discount * density / (inputs.underlying_price * sigma * sqrt_t);
+    let vega = underlying * density * sqrt_t;
+    let vanna = -carry_discount * density * d2 / sigma;
+    let volga = vega * d1 * d2 / sigma;
+    let time_decay = -underlying * density * sigma / (2.0 * sqrt_t);
+    let charm_common = density * (b / (sigma * sqrt_t) - d2 / (2.0 * t));
+
+    let (delta, theta, charm, rho) = if inputs.is_call() {
+        (
+            carry_discount * normal_cdf(d1),
+            time_decay - (b - r) * underlying * normal_cdf(d1) - r * strike * normal_cdf(d2),
+            -carry_discount * (charm_common + (b - r) * normal_cdf(d1)),
+            strike * t * normal_cdf(d2),
+        )
+    } else {
+        (
+            carry_discount * (normal_cdf(d1) - 1.0),
+            time_decay + (b - r) * underlying * normal_cdf(-d1) + r * strike * normal_cdf(-d2),
+            -carry_discount * (charm_common - (b - r) * normal_cdf(-d1)),
+            -strike * t * normal_cdf(-d2),
+        )
+    };
+
+    // On a forward the whole premium is discounted, so rho is just -T * price
+    let rho = match inputs.model {
+        PricingModel::BlackScholes => rho,
+        PricingModel::Black76 => -t * option_price(inputs),
+    };
+
+    OptionGreeks { delta, gamma, vega, theta, rho, vanna, volga, charm }
+}
+
+// Newton-Raphson from a Brenner-Subrahmanyam style starting point, falling
+// back to Brent's method when vega vanishes or Newton leaves the bracket
+pub fn implied_volatility(price: f64, inputs: &OptionInputs) -> Result<f64, ImpliedVolError> {
+    if inputs.time_to_expiry <= 0.0 {
+        return Err(ImpliedVolError::Expired);
+    }
+
+    let (lower, upper) = inputs.price_bounds();
+    let tolerance = 1e-10 * upper.max(1.0);
+    if price < lower - tolerance || price >= upper {
+        return Err(ImpliedVolError::ArbitrageBound { price, lower, upper });
+    }
+    if price <= lower + tolerance {
+        return Err(ImpliedVolError::NoTimeValue { price });
+    }
+
+    let objective = |volatility: f64| option_price(&inputs.with_volatility(volatility)) - price;
+
+    let (forward_underlying, _) = inputs.discounted_legs();
+    let mut volatility = ((2.0 * std::f64::consts::PI / inputs.time_to_expiry).sqrt() * price / forward_underlying)
+        .clamp(0.01, 3.0);
+    for _ in 0..50 {
+        let trial = inputs.with_volatility(volatility);
+        let difference = option_price(&trial) - price;
+        if difference.abs() <= tolerance {
+            return Ok(volatility);
+        }
+
+        let vega = option_greeks(&trial).vega;
+        if vega < 1e-12 {
+            break;
+        }
+        let next = volatility - difference / vega;
+        if !(MIN_IMPLIED_VOLATILITY..=MAX_IMPLIED_VOLATILITY).contains(&next) {
+            break;
+        }
+        volatility = next;
+    }
+
+    brent_root(&objective, MIN_IMPLIED_VOLATILITY, MAX_IMPLIED_VOLATILITY, 1e-12, 200)
+        .ok_or(ImpliedVolError::NoConvergence { price })
+}
+
+// Brent's method on a bracketing interval
+pub fn brent_root(f: &dyn Fn(f64) -> f64, lower: f64, upper: f64, tolerance: f64, max_iterations: usize) -> Option<f64> {
+    let (mut a, mut b) = (lower, upper);
+    let (mut fa, mut fb) = (f(a), f(b));
+    if fa * fb > 0.0 {
+        return None;
+    }
+    if fa.abs() < fb.abs() {
+        std::mem::swap(&mut a, &mut b);
+        std::mem::swap(&mut fa, &mut fb);
+    }
+
+    let (mut c, mut fc) = (a, fa);
+    let mut d = b - a;
+    let mut bisected = true;
+
+    for _ in 0..max_iterations {
+        if fb == 0.0 || (b - a).abs() < tolerance {
+            return Some(b);
+        }
+
+        let mut s = if fa != fc && fb != fc {
+            // Inverse quadratic interpolation
+            a * fb * fc / ((fa - fb) * (fa - fc)) +
+                b * fa * fc / ((fb - fa) * (fb - fc)) +
+                c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            // Secant
+            b - fb * (b - a) / (fb - fa)
+        };
+
+        let between = (s - (3.0 * a + b) / 4.0) * (s - b) < 0.0;
+        let slow = if bisected {
+            (s - b).abs() >= (b - c).abs() / 2.0 || (b - c).abs() < tolerance
+        } else {
+            (s - b).abs() >= (c - d).abs() / 2.0 || (c - d).abs() < tolerance
+        };
+        bisected = !between || slow;
+        if bisected {
+            s = (a + b) / 2.0;
+        }
+
+        let fs = f(s);
+        d = c;
+        c = b;
+        fc = fb;
+        if fa * fs < 0.0 {
+            b = s;
+            fb = fs;
+        } else {
+            a = s;
+            fa = fs;
+        }
+        if fa.abs() < fb.abs() {
+            std::mem::swap(&mut a, &mut b);
+            std::mem::swap(&mut fa, &mut fb);
+        }
+    }
+
+    None
+}
+
+impl OptionQuote {
+    pub fn mid_price(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+
+    pub fn time_to_expiry(&self) -> f64 {
+        (self.expiry - self.timestamp) / MILLIS_PER_YEAR
+    }
+
+    pub fn inputs(&self, volatility: f64) -> OptionInputs {
+        OptionInputs {
+            model: self.model,
+            option_type: self.option_type.clone(),
+            underlying_price: self.underlying_price,
+            strike: self.strike,
+            time_to_expiry: self.time_to_expiry(),
+            rate: self.rate,
+            dividend_yield: self.dividend_yield,
+            volatility,
+        }
+    }
+
+    pub fn instrument(&self) -> InstrumentType {
+        InstrumentType::Option {
+            underlying: self.underlying.clone(),
+            strike: self.strike,
+            expiry: self.expiry,
+            option_type: self.option_type.clone(),
+        }
+    }
+}
+
+// Solves the quote's mid for implied volatility, records it on the model and
+// compares it with the model's realized and GARCH estimates over the option's life
+pub fn analyze_option_quote(quote: &OptionQuote, volatility_model: &mut VolatilityModel) -> Result<OptionAnalytics, ImpliedVolError> {
+    let implied = implied_volatility(quote.mid_price(), &quote.inputs(0.0))?;
+    volatility_model.set_implied_volatility(implied);
+
+    let horizon_days = (quote.time_to_expiry() * TRADING_DAYS_PER_YEAR).ceil().max(1.0) as usize;
+    let realized_volatility = volatility_model.get_volatility();
+    Ok(OptionAnalytics {
+        symbol: quote.symbol.clone(),
+        implied_volatility: implied,
+        greeks: option_greeks(&quote.inputs(implied)),
+        realized_volatility,
+        garch_forecast: volatility_model.forecast_volatility(horizon_days),
+        volatility_premium: implied - realized_volatility,
+    })
+}
+
+pub fn normal_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+// Standard normal CDF to double precision (Hart 1968, as given by West 2005)
+pub fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs();
+    let tail = if z > 37.0 {
+        0.0
+    } else if z < 7.07106781186547 {
+        let horner = |coefficients: &[f64]| coefficients.iter().fold(0.0, |acc, c| acc * z + c);
+        let numerator = horner(&[
+            3.52624965998911e-2, 0.700383064443688, 6.37396220353165, 33.912866078383,
+            112.079291497871, 221.213596169931, 220.206867912376,
+        ]) * (-z * z / 2.0).exp();
+        let denominator = horner(&[
+            8.83883476483184e-2, 1.75566716318264, 16.064177579207, 86.7807322029461,
+            296.564248779674, 637.333633378831, 793.826512519948, 440.413735824752,
+        ]);
+        numerator / denominator
+    } else {
+        let continued = z + 1.0 / (z + 2.0 / (z + 3.0 / (z + 4.0 / (z + 0.65))));
+        (-z * z / 2.0).exp() / continued / 2.506628274631
+    };
+
+    if x > 0.0 { 1.0 - tail } else { tail }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(model: PricingModel, option_type: OptionType, strike: f64) -> OptionInputs {
+        OptionInputs {
+            model,
+            option_type,
+            underlying_price: 100.0,
+            strike,
+            time_to_expiry: 0.75,
+            rate: 0.05,
+            dividend_yield: 0.02,
+            volatility: 0.3,
+        }
+    }
+
+    fn bumped(base: &OptionInputs, bump: impl Fn(&mut OptionInputs)) -> OptionInputs {
+        let mut inputs = base.clone();
+        bump(&mut inputs);
+        inputs
+    }
+
+    // Central difference of a function of the inputs
+    fn central(base: &OptionInputs, h: f64, value: impl Fn(&OptionInputs) -> f64,
+               bump: impl Fn(&mut OptionInputs, f64)) -> f64 {
+        let up = bumped(base, |inputs| bump(inputs, h));
+        let down = bumped(base, |inputs| bump(inputs, -h));
+        (value(&up) - value(&down)) / (2.0 * h)
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64, what: &str) {
+        assert!((actual - expected).abs() <= tolerance * expected.abs().max(1.0),
+                "{}: {} vs {}", what, actual, expected);
+    }
+
+    #[test]
+    fn put_call_parity() {
+        for model in [PricingModel::BlackScholes, PricingModel::Black76] {
+            for strike in [70.0, 100.0, 140.0] {
+                let call = inputs(model, OptionType::Call, strike);
+                let put = inputs(model, OptionType::Put, strike);
+                let (underlying, discounted_strike) = call.discounted_legs();
+                assert_close(option_price(&call) - option_price(&put), underlying - discounted_strike,
+                             1e-12, "call - put");
+            }
+        }
+    }
+
+    #[test]
+    fn implied_volatility_round_trip() {
+        for model in [PricingModel::BlackScholes, PricingModel::Black76] {
+            for option_type in [OptionType::Call, OptionType::Put] {
+                // Deep in the money at low volatility the price is intrinsic to
+                // double precision, which is the NoTimeValue case below
+                for strike in [80.0, 90.0, 100.0, 115.0, 130.0] {
+                    for volatility in [0.1, 0.3, 1.5] {
+                        let base = inputs(model, option_type.clone(), strike).with_volatility(volatility);
+                        let price = option_price(&base);
+                        let recovered = implied_volatility(price, &base).unwrap();
+                        assert_close(recovered, volatility, 1e-7, "implied volatility");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn greeks_match_finite_differences() {
+        let price = |inputs: &OptionInputs| option_price(inputs);
+        let delta = |inputs: &OptionInputs| option_greeks(inputs).delta;
+        let vega = |inputs: &OptionInputs| option_greeks(inputs).vega;
+
+        for model in [PricingModel::BlackScholes, PricingModel::Black76] {
+            for option_type in [OptionType::Call, OptionType::Put] {
+                for strike in [80.0, 100.0, 125.0] {
+                    let base = inputs(model, option_type.clone(), strike);
+                    let greeks = option_greeks(&base);
+                    let spot = |inputs: &mut OptionInputs, h: f64| inputs.underlying_price += h;
+                    let vol = |inputs: &mut OptionInputs, h: f64| inputs.volatility += h;
+                    let rate = |inputs: &mut OptionInputs, h: f64| inputs.rate += h;
+                    // Theta and charm are per year of calendar time, so time to expiry shrinks
+                    let time = |inputs: &mut OptionInputs, h: f64| inputs.time_to_expiry -= h;
+
+                    assert_close(greeks.delta, central(&base, 1e-3, price, spot), 1e-6, "delta");
+                    assert_close(greeks.gamma, central(&base, 1e-3, delta, spot), 1e-6, "gamma");
+                    assert_close(greeks.vega, central(&base, 1e-5, price, vol), 1e-6, "vega");
+                    assert_close(greeks.theta, central(&base, 1e-5, price, time), 1e-6, "theta");
+                    assert_close(greeks.rho, central(&base, 1e-5, price, rate), 1e-6, "rho");
+                    assert_close(greeks.vanna, central(&base, 1e-5, delta, vol), 1e-6, "vanna");
+                    assert_close(greeks.volga, central(&base, 1e-5, vega, vol), 1e-6, "volga");
+                    assert_close(greeks.charm, central(&base, 1e-5, delta, time), 1e-6, "charm");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn implied_volatility_errors() {
+        let call = inputs(PricingModel::BlackScholes, OptionType::Call, 90.0);
+        let (lower, upper) = call.price_bounds();
+
+        let expired = bumped(&call, |inputs| inputs.time_to_expiry = 0.0);
+        assert_eq!(implied_volatility(5.0, &expired), Err(ImpliedVolError::Expired));
+
+        assert!(matches!(implied_volatility(lower - 0.5, &call), Err(ImpliedVolError::ArbitrageBound { .. })));
+        assert!(matches!(implied_volatility(upper, &call), Err(ImpliedVolError::ArbitrageBound { .. })));
+        assert_eq!(implied_volatility(lower, &call), Err(ImpliedVolError::NoTimeValue { price: lower }));
+    }
+}
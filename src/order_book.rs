@@ -1,7 +1,9 @@
 use std::collections::{BTreeMap, VecDeque};
 use crate::{MarketData, OrderSide};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct Level {
     pub price: f64,
     pub quantity: f64,
@@ -10,6 +12,14 @@ pub struct Level {
 
 
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnwindEstimate {
+    pub average_price: f64,
+    pub filled_quantity: f64,
+    pub unfilled_quantity: f64,
+    pub cost: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct OrderBook {
     bids: BTreeMap<String, VecDeque<Level>>, // Symbol -> Bid Levels
@@ -17,6 +27,13 @@ pub struct OrderBook {
     last_update_time: f64, // Renamed from last_update for clarity
     mid_price: f64,
     spread: f64,
+    beyond_depth_impact: f64,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OrderBook {
@@ -27,6 +44,7 @@ impl OrderBook {
             last_update_time: 0.0,
             mid_price: 0.0,
             spread: 0.0,
+            beyond_depth_impact: 0.01,
         }
     }
 
@@ -47,7 +65,7 @@ impl OrderBook {
             };
 
             self.bids.entry(symbol.clone())
-                .or_insert_with(VecDeque::new)
+                .or_default()
                 .push_back(bid_level);
         }
 
@@ -60,7 +78,7 @@ impl OrderBook {
             };
 
             self.asks.entry(symbol.clone())
-                .or_insert_with(VecDeque::new)
+                .or_default()
                 .push_back(ask_level);
         }
 
@@ -193,6 +211,60 @@ impl OrderBook {
         }
     }
 
+    // Costs crossing the latest top-of-book snapshot relative to `reference_price`.
+    // Older entries are tick history, not resting depth. Quantity beyond the
+    // visible size pays square-root impact on top of the touch price.
+    // Without a visible level on the exit side, `fallback_depth` (typically recent
+    // traded volume) stands in for the touch size and the whole quantity pays the
+    // square-root concession against it. With neither the cost is unknown.
+    pub fn estimate_unwind_cost(&self, symbol: &str, side: &OrderSide, quantity: f64, reference_price: f64, fallback_depth: f64) -> Option<UnwindEstimate> {
+        let latest = match side {
+            OrderSide::Buy => self.asks.get(symbol),
+            OrderSide::Sell => self.bids.get(symbol),
+        }.and_then(|levels| levels.back())
+            .filter(|level| level.price.is_finite() && level.price > 0.0 && level.quantity > 0.0);
+
+        let (touch_price, visible_quantity, depth) = match latest {
+            Some(level) => (level.price, level.quantity, level.quantity),
+            None => (reference_price, 0.0, fallback_depth),
+        };
+        if !(depth > 0.0 && touch_price.is_finite() && touch_price > 0.0) {
+            return None;
+        }
+
+        let filled_quantity = quantity.min(visible_quantity);
+        let remaining = quantity - filled_quantity;
+        let impact = if remaining > 0.0 {
+            self.beyond_depth_impact * (remaining / depth).sqrt()
+        } else {
+            0.0
+        };
+        let beyond_price = match side {
+            OrderSide::Buy => touch_price * (1.0 + impact),
+            OrderSide::Sell => touch_price * (1.0 - impact).max(0.0),
+        };
+        let total_notional = filled_quantity * touch_price + remaining * beyond_price;
+        let average_price = if quantity > 0.0 { total_notional / quantity } else { reference_price };
+
+        let cost = match side {
+            OrderSide::Buy => (average_price - reference_price) * quantity,
+            OrderSide::Sell => (reference_price - average_price) * quantity,
+        };
+
+        Some(UnwindEstimate {
+            average_price,
+            filled_quantity,
+            unfilled_quantity: remaining,
+            cost: cost.max(0.0),
+        })
+    }
+
+    // Price concession for size beyond the visible touch, as a fraction of price
+    // at one multiple of the visible size
+    pub fn set_beyond_depth_impact(&mut self, impact: f64) {
+        self.beyond_depth_impact = impact.max(0.0);
+    }
+
     pub fn get_stats(&self, symbol: &str) -> crate::OrderBookStats {
         crate::OrderBookStats {
             bid_ask_spread: self.spread,
@@ -0,0 +1,276 @@
+use crate::*;
+
+// Markov-switching volatility: returns are N(0, sigma_k^2) in hidden regime k,
+// with regimes following a first-order Markov chain. Fitted by EM
+// (Baum-Welch) on a return window and filtered online with the Hamilton filter.
+// States are kept ordered from calmest to most volatile.
+#[derive(Debug, Clone)]
+pub struct MarkovSwitchingModel {
+    num_states: usize,
+    variances: Vec<f64>,
+    transition: Vec<Vec<f64>>, // transition[i][j] = P(next = j | current = i)
+    filtered: Vec<f64>,
+    fitted: bool,
+    log_likelihood: f64,
+    max_iterations: usize,
+    tolerance: f64,
+}
+
+// Filtered regime view for quoting and reporting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegimeState {
+    pub probabilities: Vec<f64>,
+    pub volatilities: Vec<f64>,           // Annualized volatility of each regime
+    pub expected_durations_secs: Vec<f64>,
+    pub most_likely: usize,
+    pub log_likelihood: f64,
+}
+
+impl MarkovSwitchingModel {
+    pub fn new(num_states: usize) -> Self {
+        let num_states = num_states.clamp(2, 4);
+        let stay = 0.95;
+        let leave = (1.0 - stay) / (num_states - 1) as f64;
+        Self {
+            num_states,
+            variances: vec![0.0; num_states],
+            transition: (0..num_states)
+                .map(|i| (0..num_states).map(|j| if i == j { stay } else { leave }).collect())
+                .collect(),
+            filtered: vec![1.0 / num_states as f64; num_states],
+            fitted: false,
+            log_likelihood: 0.0,
+            max_iterations: 100,
+            tolerance: 1e-6,
+        }
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn densities(&self, r: f64) -> Vec<f64> {
+        self.variances.iter()
+            .map(|&variance| {
+                let variance = variance.max(1e-30);
+                (-0.5 * r * r / variance).exp() / (2.0 * std::f64::consts::PI * variance).sqrt()
+            })
+            .collect()
+    }
+
+    // Predict with the transition matrix, then weight by each regime's density
+    fn filter_step(&self, prior: &[f64], r: f64) -> (Vec<f64>, f64) {
+        let densities = self.densities(r);
+        let mut posterior: Vec<f64> = (0..self.num_states)
+            .map(|j| {
+                let predicted: f64 = (0..self.num_states).map(|i| prior[i] * self.transition[i][j]).sum();
+                predicted * densities[j]
+            })
+            .collect();
+        let scale: f64 = posterior.iter().sum();
+        if scale > 0.0 && scale.is_finite() {
+            posterior.iter_mut().for_each(|p| *p /= scale);
+            (posterior, scale)
+        } else {
+            // Return far outside every regime: fall back to the prediction
+            (prior.to_vec(), 0.0)
+        }
+    }
+
+    // Updates the filtered probabilities with one return
+    pub fn filter(&mut self, r: f64) {
+        if !self.fitted {
+            return;
+        }
+        self.filtered = self.filter_step(&self.filtered, r).0;
+    }
+
+    // EM fit on returns already scaled to a common interval. The filtered
+    // probabilities are rebuilt over the window afterwards.
+    pub fn fit(&mut self, returns: &[f64]) -> bool {
+        let n = returns.len();
+        let k = self.num_states;
+        if n < 20 * k {
+            return false;
+        }
+
+        let sample_variance = returns.iter().map(|r| r * r).sum::<f64>() / n as f64;
+        if sample_variance <= 0.0 {
+            return false;
+        }
+        if !self.fitted {
+            // Spread the starting variances geometrically around the sample variance
+            self.variances = (0..k)
+                .map(|i| sample_variance * 4.0_f64.powf(i as f64 / (k - 1) as f64 - 0.5))
+                .collect();
+        }
+
+        let mut previous_log_likelihood = f64::NEG_INFINITY;
+        for _ in 0..self.max_iterations {
+            // Forward pass with scaling
+            let initial = vec![1.0 / k as f64; k];
+            let mut alpha = Vec::with_capacity(n);
+            let mut scales = Vec::with_capacity(n);
+            let mut prior = initial;
+            for &r in returns {
+                let (posterior, scale) = self.filter_step(&prior, r);
+                scales.push(scale.max(1e-300));
+                alpha.push(posterior.clone());
+                prior = posterior;
+            }
+            let log_likelihood: f64 = scales.iter().map(|s| s.ln()).sum();
+
+            // Backward pass
+            let mut beta = vec![vec![1.0; k]; n];
+            for t in (0..n - 1).rev() {
+                let densities = self.densities(returns[t + 1]);
+                for i in 0..k {
+                    beta[t][i] = (0..k)
+                        .map(|j| self.transition[i][j] * densities[j] * beta[t + 1][j])
+                        .sum::<f64>() / scales[t + 1];
+                }
+            }
+
+            // Smoothed state and transition probabilities
+            let mut variance_numerator = vec![0.0; k];
+            let mut occupancy = vec![0.0; k];
+            let mut transitions = vec![vec![0.0; k]; k];
+            for t in 0..n {
+                let gamma: Vec<f64> = (0..k).map(|i| alpha[t][i] * beta[t][i]).collect();
+                let total: f64 = gamma.iter().sum();
+                for i in 0..k {
+                    let weight = if total > 0.0 { gamma[i] / total } else { 0.0 };
+                    variance_numerator[i] += weight * returns[t] * returns[t];
+                    occupancy[i] += weight;
+                }
+
+                if t + 1 < n {
+                    let densities = self.densities(returns[t + 1]);
+                    for (i, row) in transitions.iter_mut().enumerate() {
+                        for (j, count) in row.iter_mut().enumerate() {
+                            *count += alpha[t][i] * self.transition[i][j] * densities[j] * beta[t + 1][j] / scales[t + 1];
+                        }
+                    }
+                }
+            }
+
+            for i in 0..k {
+                if occupancy[i] > 1e-9 {
+                    self.variances[i] = (variance_numerator[i] / occupancy[i]).max(sample_variance * 1e-4);
+                }
+                let row_total: f64 = transitions[i].iter().sum();
+                if row_total > 0.0 {
+                    for (probability, count) in self.transition[i].iter_mut().zip(transitions[i].iter()) {
+                        *probability = (count / row_total).max(1e-6);
+                    }
+                    let normalizer: f64 = self.transition[i].iter().sum();
+                    self.transition[i].iter_mut().for_each(|p| *p /= normalizer);
+                }
+            }
+
+            self.filtered = alpha[n - 1].clone();
+            self.log_likelihood = log_likelihood;
+            if (log_likelihood - previous_log_likelihood).abs() < self.tolerance * log_likelihood.abs().max(1.0) {
+                break;
+            }
+            previous_log_likelihood = log_likelihood;
+        }
+
+        self.sort_states();
+        self.fitted = true;
+        true
+    }
+
+    // Keeps regime 0 the calmest so callers can index regimes consistently across refits
+    fn sort_states(&mut self) {
+        let mut order: Vec<usize> = (0..self.num_states).collect();
+        order.sort_by(|&a, &b| self.variances[a].partial_cmp(&self.variances[b]).unwrap_or(std::cmp::Ordering::Equal));
+
+        self.variances = order.iter().map(|&i| self.variances[i]).collect();
+        self.filtered = order.iter().map(|&i| self.filtered[i]).collect();
+        self.transition = order.iter()
+            .map(|&i| order.iter().map(|&j| self.transition[i][j]).collect())
+            .collect();
+    }
+
+    // Expected number of observations spent in each regime once entered
+    pub fn expected_durations(&self) -> Vec<f64> {
+        (0..self.num_states)
+            .map(|i| 1.0 / (1.0 - self.transition[i][i]).max(1e-9))
+            .collect()
+    }
+
+    pub fn state(&self, seconds_per_observation: f64) -> RegimeState {
+        let most_likely = self.filtered.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+
+        RegimeState {
+            probabilities: self.filtered.clone(),
+            volatilities: self.variances.iter().map(|v| annualize(v.sqrt())).collect(),
+            expected_durations_secs: self.expected_durations().iter()
+                .map(|d| d * seconds_per_observation)
+                .collect(),
+            most_likely,
+            log_likelihood: self.log_likelihood,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two-regime chain with the given per-observation volatilities and stay
+    // probabilities; returns the returns and the regime of each
+    fn simulate(seed: u64, count: usize, volatilities: [f64; 2], stay: [f64; 2]) -> (Vec<f64>, Vec<usize>) {
+        let mut rng = SimulationRng::new(seed);
+        let mut regime = 0;
+        let mut returns = Vec::with_capacity(count);
+        let mut regimes = Vec::with_capacity(count);
+        for _ in 0..count {
+            if rng.next_uniform() > stay[regime] {
+                regime = 1 - regime;
+            }
+            returns.push(volatilities[regime] * rng.next_normal());
+            regimes.push(regime);
+        }
+        (returns, regimes)
+    }
+
+    #[test]
+    fn em_recovers_two_volatility_regimes() {
+        let (returns, regimes) = simulate(29, 6000, [1e-3, 4e-3], [0.99, 0.98]);
+        let mut model = MarkovSwitchingModel::new(2);
+        assert!(model.fit(&returns));
+
+        let volatilities: Vec<f64> = model.variances.iter().map(|v| v.sqrt()).collect();
+        assert!((volatilities[0] / 1e-3 - 1.0).abs() < 0.1, "calm volatility {}", volatilities[0]);
+        assert!((volatilities[1] / 4e-3 - 1.0).abs() < 0.1, "stressed volatility {}", volatilities[1]);
+        assert!((model.transition[0][0] - 0.99).abs() < 0.01, "calm stay {}", model.transition[0][0]);
+        assert!((model.transition[1][1] - 0.98).abs() < 0.015, "stressed stay {}", model.transition[1][1]);
+
+        // Fitted likelihood beats a single normal at the sample variance
+        let variance = returns.iter().map(|r| r * r).sum::<f64>() / returns.len() as f64;
+        let single: f64 = returns.iter()
+            .map(|r| -0.5 * ((2.0 * std::f64::consts::PI * variance).ln() + r * r / variance))
+            .sum();
+        assert!(model.log_likelihood > single);
+
+        // Filtering from scratch classifies most observations correctly
+        let mut filtered = MarkovSwitchingModel { filtered: vec![0.5, 0.5], ..model.clone() };
+        let correct = returns.iter().zip(&regimes)
+            .filter(|&(&r, &regime)| {
+                filtered.filter(r);
+                filtered.state(1.0).most_likely == regime
+            })
+            .count();
+        assert!(correct as f64 / returns.len() as f64 > 0.9, "{} of {} classified", correct, returns.len());
+    }
+}
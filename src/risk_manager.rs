@@ -1,6 +1,7 @@
 
 use crate::*;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone)]
 pub struct RiskManager {
@@ -9,6 +10,17 @@ pub struct RiskManager {
     exposure_limits: ExposureLimits,
     var_calculator: VarCalculator,
     drawdown_monitor: DrawdownMonitor,
+    pre_trade_controls: PreTradeControls,
+    group_limits: GroupLimits,
+    instruments: HashMap<String, RegisteredInstrument>,
+    response_ladder: ResponseLadder,
+    kill_switch: KillSwitch,
+    liquidity_monitor: LiquidityMonitor,
+    audit_log: RiskAuditLog,
+    limit_hierarchy: LimitHierarchy,
+    margin_account: MarginAccount,
+    current_time: f64,
+    rejection_counts: HashMap<String, u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +33,7 @@ struct RiskConfig {
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct PositionLimits {
     max_gross_notional: f64,
     max_net_notional: f64,
@@ -31,17 +44,211 @@ struct PositionLimits {
 #[derive(Debug, Clone)]
 struct ExposureLimits {
     gross_exposure_limit: f64,
-    net_exposure_limit: f64,
     delta_limit: f64,
     gamma_limit: f64,
 }
 
+// Halvings used to find the largest quote size that passes the pre-trade checks
+const QUOTE_SIZE_BISECTIONS: usize = 12;
+
+// Escalating automatic actions, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RiskResponse {
+    Normal,
+    WidenSpreads,
+    ReduceSize,
+    ReduceOnly,
+    CancelAll,
+    Flatten,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseStep {
+    pub utilization: f64,
+    pub response: RiskResponse,
+}
+
+#[derive(Debug, Clone)]
+struct ResponseLadder {
+    steps: Vec<ResponseStep>,
+    spread_widen_factor: f64,
+    size_reduction_factor: f64,
+    current_response: RiskResponse,
+    current_utilization: f64,
+    breached_limits: Vec<String>,
+    // Flatten quantity sent per symbol and not yet filled
+    pending_flatten: HashMap<String, f64>,
+}
+
+#[derive(Debug, Clone)]
+struct LiquidityMonitor {
+    traded_volume: HashMap<String, VecDeque<(f64, f64)>>,
+    volume_window_ms: f64,
+    participation_rate: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionLiquidity {
+    pub symbol: String,
+    pub quantity: f64,
+    // None when there is neither visible depth nor traded volume to price the exit
+    pub unwind_cost: Option<f64>,
+    pub unfilled_quantity: f64,
+    pub time_to_liquidate_secs: f64,
+}
+
+// Portfolio as it would stand if every outstanding quote on the given side(s) filled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteExposure {
+    pub scenario: QuoteFillScenario,
+    pub gross_exposure: f64,
+    pub net_exposure: f64,
+    pub concentration: f64,
+    pub var: f64,
+    pub breached_limits: Vec<LimitBreach>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QuoteFillScenario {
+    BidsFilled,
+    AsksFilled,
+    BothFilled,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KillSwitch {
+    pub engaged: bool,
+    pub reason: String,
+    pub engaged_at: f64,
+}
+
+#[derive(Debug, Clone)]
+struct RegisteredInstrument {
+    instrument: InstrumentType,
+    greeks: Greeks,
+}
+
+#[derive(Debug, Clone)]
+struct GroupLimits {
+    classifications: HashMap<String, SymbolClassification>,
+    limits: HashMap<String, GroupLimit>,
+}
+
+// Buckets a symbol belongs to for group-level exposure limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SymbolClassification {
+    pub sector: String,
+    pub indices: Vec<String>,
+    pub underlying: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GroupKind {
+    Sector,
+    Index,
+    Underlying,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupLimit {
+    pub max_gross: f64,
+    pub max_net: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroupExposure {
+    pub group: String,
+    pub gross_exposure: f64,
+    pub net_exposure: f64,
+    pub gross_limit: f64,
+    pub net_limit: f64,
+    pub utilization: f64,
+}
+
+#[derive(Debug, Clone)]
+struct PreTradeControls {
+    collar_reference: CollarReference,
+    price_collar_pct: f64,
+    default_max_order_quantity: f64,
+    default_max_order_notional: f64,
+    symbol_order_limits: HashMap<String, SymbolOrderLimits>,
+    max_orders_per_second: usize,
+    max_messages_per_second: usize,
+    throttle_window_ms: f64,
+    duplicate_window_ms: f64,
+    reference_prices: HashMap<String, ReferencePrice>,
+    order_timestamps: VecDeque<f64>,
+    message_timestamps: VecDeque<f64>,
+    recent_orders: VecDeque<Order>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CollarReference {
+    Mid,
+    LastTrade,
+}
+
+#[derive(Debug, Clone)]
+struct SymbolOrderLimits {
+    max_order_quantity: f64,
+    max_order_notional: f64,
+}
+
+#[derive(Debug, Clone)]
+struct ReferencePrice {
+    mid_price: f64,
+    last_trade: f64,
+}
+
 #[derive(Debug, Clone)]
 struct VarCalculator {
     confidence_level: f64,
-    time_horizon: f64,
+    time_horizon_days: f64, // VaR horizon in trading days
     historical_returns: VecDeque<f64>,
     correlation_matrix: HashMap<String, HashMap<String, f64>>,
+    covariance_estimator: CovarianceEstimator,
+    monte_carlo: MonteCarloConfig,
+    confidence_levels: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReturnDistribution {
+    Normal,
+    StudentT { degrees_of_freedom: f64 },
+}
+
+#[derive(Debug, Clone)]
+struct MonteCarloConfig {
+    paths: usize,
+    horizon_days: f64,
+    distribution: ReturnDistribution,
+    seed: u64,
+    refresh_interval_ms: f64, // Simulation reruns at most this often from evaluate_risk
+    last_run_time: Option<f64>,
+    last_result: Vec<TailRiskEstimate>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TailRiskEstimate {
+    pub confidence_level: f64,
+    pub var: f64,
+    pub expected_shortfall: f64,
+}
+
+// SplitMix64 generator; seeded per simulation so results are reproducible
+#[derive(Debug, Clone)]
+pub(crate) struct SimulationRng {
+    state: u64,
+    spare_normal: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionVar {
+    pub symbol: String,
+    pub exposure: f64,
+    pub marginal_var: f64,
+    pub component_var: f64,
+    pub contribution_pct: f64,
 }
 
 #[derive(Debug, Clone)]
@@ -50,9 +257,104 @@ struct DrawdownMonitor {
     current_drawdown: f64,
     peak_equity: f64,
     daily_pnl: f64,
+    current_equity: f64, // Cash plus marked positions, as of the last mark
+    day_start_equity: f64,
+    intraday_peak_equity: f64,
+    intraday_drawdown: f64,
+    max_observed_drawdown: f64,
+    rollover_offset_ms: f64,
+    current_trading_day: Option<i64>,
+}
+
+// Pre-trade check that rejected an order, with the limit and the value that breached it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RiskRejection {
+    SingleOrderNotional { symbol: String, limit: f64, attempted: f64 },
+    PositionSize { symbol: String, limit: f64, attempted: f64 },
+    GrossExposure { symbol: String, limit: f64, attempted: f64 },
+    Concentration { symbol: String, limit: f64, attempted: f64 },
+    OrderQuantity { symbol: String, limit: f64, attempted: f64 },
+    OrderNotional { symbol: String, limit: f64, attempted: f64 },
+    PriceCollar { symbol: String, limit: f64, attempted: f64 },
+    OrderRate { symbol: String, limit: f64, attempted: f64 },
+    MessageRate { symbol: String, limit: f64, attempted: f64 },
+    DuplicateOrder { symbol: String, limit: f64, attempted: f64 },
+    DailyLoss { symbol: String, limit: f64, attempted: f64 },
+    Drawdown { symbol: String, limit: f64, attempted: f64 },
+    NetNotional { symbol: String, limit: f64, attempted: f64 },
+    GroupGrossExposure { symbol: String, limit: f64, attempted: f64 },
+    GroupNetExposure { symbol: String, limit: f64, attempted: f64 },
+    DeltaLimit { symbol: String, limit: f64, attempted: f64 },
+    GammaLimit { symbol: String, limit: f64, attempted: f64 },
+    ReduceOnly { symbol: String, limit: f64, attempted: f64 },
+    KillSwitch { symbol: String, limit: f64, attempted: f64 },
+    HierarchyOrderNotional { symbol: String, limit: f64, attempted: f64 },
+    HierarchyGrossExposure { symbol: String, limit: f64, attempted: f64 },
+    HierarchyNetExposure { symbol: String, limit: f64, attempted: f64 },
+    BuyingPower { symbol: String, limit: f64, attempted: f64 },
+}
+
+impl RiskRejection {
+    fn parts(&self) -> (&'static str, &str, f64, f64) {
+        match self {
+            RiskRejection::SingleOrderNotional { symbol, limit, attempted } => ("SingleOrderNotional", symbol, *limit, *attempted),
+            RiskRejection::PositionSize { symbol, limit, attempted } => ("PositionSize", symbol, *limit, *attempted),
+            RiskRejection::GrossExposure { symbol, limit, attempted } => ("GrossExposure", symbol, *limit, *attempted),
+            RiskRejection::Concentration { symbol, limit, attempted } => ("Concentration", symbol, *limit, *attempted),
+            RiskRejection::OrderQuantity { symbol, limit, attempted } => ("OrderQuantity", symbol, *limit, *attempted),
+            RiskRejection::OrderNotional { symbol, limit, attempted } => ("OrderNotional", symbol, *limit, *attempted),
+            RiskRejection::PriceCollar { symbol, limit, attempted } => ("PriceCollar", symbol, *limit, *attempted),
+            RiskRejection::OrderRate { symbol, limit, attempted } => ("OrderRate", symbol, *limit, *attempted),
+            RiskRejection::MessageRate { symbol, limit, attempted } => ("MessageRate", symbol, *limit, *attempted),
+            RiskRejection::DuplicateOrder { symbol, limit, attempted } => ("DuplicateOrder", symbol, *limit, *attempted),
+            RiskRejection::DailyLoss { symbol, limit, attempted } => ("DailyLoss", symbol, *limit, *attempted),
+            RiskRejection::Drawdown { symbol, limit, attempted } => ("Drawdown", symbol, *limit, *attempted),
+            RiskRejection::NetNotional { symbol, limit, attempted } => ("NetNotional", symbol, *limit, *attempted),
+            RiskRejection::GroupGrossExposure { symbol, limit, attempted } => ("GroupGrossExposure", symbol, *limit, *attempted),
+            RiskRejection::GroupNetExposure { symbol, limit, attempted } => ("GroupNetExposure", symbol, *limit, *attempted),
+            RiskRejection::DeltaLimit { symbol, limit, attempted } => ("DeltaLimit", symbol, *limit, *attempted),
+            RiskRejection::GammaLimit { symbol, limit, attempted } => ("GammaLimit", symbol, *limit, *attempted),
+            RiskRejection::ReduceOnly { symbol, limit, attempted } => ("ReduceOnly", symbol, *limit, *attempted),
+            RiskRejection::KillSwitch { symbol, limit, attempted } => ("KillSwitch", symbol, *limit, *attempted),
+            RiskRejection::HierarchyOrderNotional { symbol, limit, attempted } => ("HierarchyOrderNotional", symbol, *limit, *attempted),
+            RiskRejection::HierarchyGrossExposure { symbol, limit, attempted } => ("HierarchyGrossExposure", symbol, *limit, *attempted),
+            RiskRejection::HierarchyNetExposure { symbol, limit, attempted } => ("HierarchyNetExposure", symbol, *limit, *attempted),
+            RiskRejection::BuyingPower { symbol, limit, attempted } => ("BuyingPower", symbol, *limit, *attempted),
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        self.parts().0
+    }
+
+    pub fn symbol(&self) -> &str {
+        self.parts().1
+    }
+
+    pub fn limit(&self) -> f64 {
+        self.parts().2
+    }
+
+    pub fn attempted(&self) -> f64 {
+        self.parts().3
+    }
+}
+
+impl fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} limit exceeded for {}: attempted {:.2}, limit {:.2}",
+               self.reason(), self.symbol(), self.attempted(), self.limit())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitBreach {
+    pub limit: String,
+    pub limit_value: f64,
+    pub stressed_value: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RiskMetrics {
     pub var_95: f64,
     pub var_99: f64,
@@ -63,6 +365,143 @@ pub struct RiskMetrics {
     pub leverage: f64,
     pub concentration_risk: f64,
     pub risk_score: f64,
+    pub equity: f64,
+    pub daily_pnl: f64,
+    pub intraday_drawdown: f64,
+    pub group_exposures: Vec<GroupExposure>,
+    // Dollar delta, dollar gamma per 1% move, vega per vol point, theta per day
+    pub portfolio_greeks: Greeks,
+    pub liquidity_adjusted_var: f64,
+    pub total_unwind_cost: f64,
+    pub max_time_to_liquidate_secs: f64,
+    pub position_liquidity: Vec<PositionLiquidity>,
+    pub quote_exposures: Vec<QuoteExposure>,
+    pub limit_nodes: Vec<NodeUtilization>,
+    pub margin: MarginStatus,
+    pub parametric_var_95: f64,
+    pub parametric_var_99: f64,
+    pub monte_carlo_tail_risk: Vec<TailRiskEstimate>,
+    pub position_var: Vec<PositionVar>,
+}
+
+impl SimulationRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed, spare_normal: None }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform on the open interval (0, 1)
+    pub(crate) fn next_uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    // Box-Muller, caching the second variate
+    pub(crate) fn next_normal(&mut self) -> f64 {
+        if let Some(spare) = self.spare_normal.take() {
+            return spare;
+        }
+
+        let u1 = self.next_uniform();
+        let u2 = self.next_uniform();
+        let radius = (-2.0 * u1.ln()).sqrt();
+        let angle = 2.0 * std::f64::consts::PI * u2;
+        self.spare_normal = Some(radius * angle.sin());
+        radius * angle.cos()
+    }
+
+    // Marsaglia-Tsang gamma(shape, 1) sampler; shape must be positive and finite
+    fn next_gamma(&mut self, shape: f64) -> f64 {
+        if !(shape > 0.0 && shape.is_finite()) {
+            return f64::NAN;
+        }
+        if shape < 1.0 {
+            let u = self.next_uniform();
+            return self.next_gamma(shape + 1.0) * u.powf(1.0 / shape);
+        }
+
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            let x = self.next_normal();
+            let v = (1.0 + c * x).powi(3);
+            if v <= 0.0 {
+                continue;
+            }
+            let u = self.next_uniform();
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+
+    fn next_chi_squared(&mut self, degrees_of_freedom: f64) -> f64 {
+        2.0 * self.next_gamma(degrees_of_freedom / 2.0)
+    }
+}
+
+// Lower-triangular Cholesky factor, with a small diagonal jitter for
+// covariance estimates that are only positive semi-definite
+pub fn cholesky_decomposition(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = matrix.len();
+    let max_diagonal = (0..n).map(|i| matrix[i][i]).fold(0.0f64, f64::max);
+    let jitter = max_diagonal * 1e-10;
+    let mut lower = vec![vec![0.0; n]; n];
+
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                lower[i][j] = (matrix[i][i] + jitter - sum).max(0.0).sqrt();
+            } else if lower[j][j] > 0.0 {
+                lower[i][j] = (matrix[i][j] - sum) / lower[j][j];
+            }
+        }
+    }
+
+    lower
+}
+
+// Inverse of the standard normal CDF (Acklam's rational approximation)
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02,
+                         1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02,
+                         6.680131188771972e+01, -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00,
+                         -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00,
+                         3.754408661907416e+00];
+
+    let p = p.clamp(1e-12, 1.0 - 1e-12);
+    let p_low = 0.02425;
+
+    if p < p_low {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) /
+            ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - p_low {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q /
+            (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) /
+            ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    }
+}
+
+impl Default for RiskManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RiskManager {
@@ -83,98 +522,885 @@ impl RiskManager {
             },
             exposure_limits: ExposureLimits {
                 gross_exposure_limit: 15000000.0,
-                net_exposure_limit: 3000000.0,
                 delta_limit: 1000000.0,
                 gamma_limit: 500000.0,
             },
             var_calculator: VarCalculator {
                 confidence_level: 0.95,
-                time_horizon: 1.0,
+                time_horizon_days: 1.0,
                 historical_returns: VecDeque::new(),
                 correlation_matrix: HashMap::new(),
+                covariance_estimator: CovarianceEstimator::new(),
+                monte_carlo: MonteCarloConfig {
+                    paths: 1000,
+                    horizon_days: 1.0,
+                    distribution: ReturnDistribution::Normal,
+                    seed: 42,
+                    refresh_interval_ms: 60_000.0,
+                    last_run_time: None,
+                    last_result: Vec::new(),
+                },
+                confidence_levels: vec![0.95, 0.99],
             },
             drawdown_monitor: DrawdownMonitor {
                 max_allowed_drawdown: 0.15,
                 current_drawdown: 0.0,
                 peak_equity: 1000000.0,
                 daily_pnl: 0.0,
+                current_equity: 1000000.0,
+                day_start_equity: 1000000.0,
+                intraday_peak_equity: 1000000.0,
+                intraday_drawdown: 0.0,
+                max_observed_drawdown: 0.0,
+                rollover_offset_ms: 0.0,
+                current_trading_day: None,
+            },
+            pre_trade_controls: PreTradeControls {
+                collar_reference: CollarReference::Mid,
+                price_collar_pct: 0.05,
+                default_max_order_quantity: 10000.0,
+                default_max_order_notional: 1000000.0,
+                symbol_order_limits: HashMap::new(),
+                max_orders_per_second: 50,
+                max_messages_per_second: 200,
+                throttle_window_ms: 1000.0,
+                duplicate_window_ms: 500.0,
+                reference_prices: HashMap::new(),
+                order_timestamps: VecDeque::new(),
+                message_timestamps: VecDeque::new(),
+                recent_orders: VecDeque::new(),
+            },
+            group_limits: GroupLimits {
+                classifications: HashMap::new(),
+                limits: HashMap::new(),
             },
+            instruments: HashMap::new(),
+            response_ladder: ResponseLadder {
+                steps: vec![
+                    ResponseStep { utilization: 0.6, response: RiskResponse::WidenSpreads },
+                    ResponseStep { utilization: 0.75, response: RiskResponse::ReduceSize },
+                    ResponseStep { utilization: 0.85, response: RiskResponse::ReduceOnly },
+                    ResponseStep { utilization: 1.0, response: RiskResponse::CancelAll },
+                    ResponseStep { utilization: 1.25, response: RiskResponse::Flatten },
+                ],
+                spread_widen_factor: 2.0,
+                size_reduction_factor: 0.5,
+                current_response: RiskResponse::Normal,
+                current_utilization: 0.0,
+                breached_limits: Vec::new(),
+                pending_flatten: HashMap::new(),
+            },
+            kill_switch: KillSwitch::default(),
+            liquidity_monitor: LiquidityMonitor {
+                traded_volume: HashMap::new(),
+                volume_window_ms: 300000.0,
+                participation_rate: 0.1,
+            },
+            audit_log: RiskAuditLog::new(),
+            limit_hierarchy: LimitHierarchy::new(NodeLimits {
+                max_gross_notional: 10000000.0,
+                max_net_notional: 2000000.0,
+                max_order_notional: f64::INFINITY,
+            }),
+            margin_account: MarginAccount::new(1000000.0),
+            current_time: 0.0,
+            rejection_counts: HashMap::new(),
+        }
+    }
+
+    pub fn update_market_data(&mut self, market_data: &MarketData) {
+        self.current_time = market_data.timestamp;
+        let mid_price = if market_data.bid_price > 0.0 && market_data.ask_price > 0.0 {
+            (market_data.bid_price + market_data.ask_price) / 2.0
+        } else {
+            market_data.last_price
+        };
+
+        self.pre_trade_controls.reference_prices.insert(market_data.symbol.clone(), ReferencePrice {
+            mid_price,
+            last_trade: market_data.last_price,
+        });
+
+        let estimator = &mut self.var_calculator.covariance_estimator;
+        if estimator.update(&market_data.symbol, market_data.last_price, market_data.timestamp) {
+            self.var_calculator.correlation_matrix = estimator.correlation_matrix();
+        }
+
+        // market_data.volume is the volume traded since the previous tick, not a
+        // cumulative session total
+        let monitor = &mut self.liquidity_monitor;
+        let volumes = monitor.traded_volume.entry(market_data.symbol.clone()).or_default();
+        volumes.push_back((market_data.timestamp, market_data.volume.max(0.0)));
+        while volumes.front().is_some_and(|&(t, _)| t < market_data.timestamp - monitor.volume_window_ms) {
+            volumes.pop_front();
+        }
+    }
+
+    pub fn set_symbol_order_limits(&mut self, symbol: &str, max_order_quantity: f64, max_order_notional: f64) {
+        self.pre_trade_controls.symbol_order_limits.insert(symbol.to_string(), SymbolOrderLimits {
+            max_order_quantity,
+            max_order_notional,
+        });
+        self.record_limit_change(Some(symbol), "Symbol order limits".to_string(), &[
+            ("max_order_quantity", max_order_quantity),
+            ("max_order_notional", max_order_notional),
+        ]);
+    }
+
+    pub fn set_price_collar(&mut self, reference: CollarReference, collar_pct: f64) {
+        let description = format!("Price collar vs {:?}", reference);
+        self.pre_trade_controls.collar_reference = reference;
+        self.pre_trade_controls.price_collar_pct = collar_pct;
+        self.record_limit_change(None, description, &[("price_collar_pct", collar_pct)]);
+    }
+
+    pub fn set_throttle_limits(&mut self, max_orders_per_second: usize, max_messages_per_second: usize) {
+        self.pre_trade_controls.max_orders_per_second = max_orders_per_second;
+        self.pre_trade_controls.max_messages_per_second = max_messages_per_second;
+        self.record_limit_change(None, "Order and message throttles".to_string(), &[
+            ("max_orders_per_second", max_orders_per_second as f64),
+            ("max_messages_per_second", max_messages_per_second as f64),
+        ]);
+    }
+
+    // Equity, PnL and ladder state attached to every audit entry
+    fn audit_snapshot(&self) -> HashMap<String, f64> {
+        let monitor = &self.drawdown_monitor;
+        let mut snapshot = HashMap::new();
+        snapshot.insert("equity".to_string(), monitor.current_equity);
+        snapshot.insert("cash".to_string(), self.margin_account.cash);
+        snapshot.insert("daily_pnl".to_string(), monitor.daily_pnl);
+        snapshot.insert("drawdown".to_string(), monitor.current_drawdown);
+        snapshot.insert("intraday_drawdown".to_string(), monitor.intraday_drawdown);
+        snapshot.insert("limit_utilization".to_string(), self.response_ladder.current_utilization);
+        snapshot
+    }
+
+    fn record_event(&mut self, timestamp: f64, event_type: RiskEventType, symbol: Option<&str>, description: String, values: &[(&str, f64)]) {
+        let mut metrics = self.audit_snapshot();
+        for (name, value) in values {
+            metrics.insert(name.to_string(), *value);
+        }
+        self.audit_log.record(timestamp, event_type, symbol, description, metrics);
+    }
+
+    fn record_limit_change(&mut self, symbol: Option<&str>, description: String, values: &[(&str, f64)]) {
+        console_log!("Risk limit changed: {}", description);
+        self.record_event(self.current_time, RiskEventType::LimitChange, symbol, description, values);
+    }
+
+    pub fn query_audit_log(&self, start_time: f64, end_time: f64, event_type: Option<RiskEventType>) -> Vec<RiskEvent> {
+        self.audit_log.query(start_time, end_time, event_type)
+    }
+
+    pub fn export_audit_log(&self, start_time: f64, end_time: f64, event_type: Option<RiskEventType>) -> String {
+        self.audit_log.export_json_lines(start_time, end_time, event_type)
+    }
+
+    pub fn set_audit_sink(&mut self, sink: Option<js_sys::Function>) {
+        self.audit_log.set_sink(sink);
+    }
+
+    pub fn set_audit_capacity(&mut self, capacity: usize) {
+        self.audit_log.set_capacity(capacity);
+    }
+
+    // Count a non-order message (cancel, modify) against the message throttle
+    pub fn record_message(&mut self, timestamp: f64) {
+        self.prune_throttle_windows(timestamp);
+        self.pre_trade_controls.message_timestamps.push_back(timestamp);
+    }
+
+    pub fn validate_order(&mut self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        self.prune_throttle_windows(order.timestamp);
+
+        let result = self.check_order_terms(order, positions)
+            .and_then(|_| self.check_order_rate(order))
+            .and_then(|_| self.check_duplicate_order(order))
+            .and_then(|_| self.check_order_impact(order, positions));
+
+        // Every submission is a message on the wire, whether or not it passes
+        self.pre_trade_controls.message_timestamps.push_back(order.timestamp);
+
+        let order_values = [("quantity", order.quantity), ("price", order.price)];
+        match &result {
+            Ok(()) => {
+                self.record_accepted_order(order);
+                self.record_event(order.timestamp, RiskEventType::CheckPassed, Some(&order.symbol),
+                                  format!("Order {} accepted", order.id), &order_values);
+            }
+            Err(rejection) => {
+                console_log!("Order rejected: {}", rejection);
+                *self.rejection_counts.entry(rejection.reason().to_string()).or_insert(0) += 1;
+                self.record_event(order.timestamp, RiskEventType::CheckRejected, Some(&order.symbol),
+                                  format!("Order {} rejected: {}", order.id, rejection), &[
+                    order_values[0],
+                    order_values[1],
+                    ("limit", rejection.limit()),
+                    ("attempted", rejection.attempted()),
+                ]);
+            }
+        }
+
+        result
+    }
+
+    // Response ladder, loss, size and price checks on the order itself
+    fn check_order_terms(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        self.check_risk_response(order, positions)
+            .and_then(|_| self.check_loss_limits(order, positions))
+            .and_then(|_| self.check_fat_finger_limits(order))
+            .and_then(|_| self.check_price_collar(order))
+    }
+
+    // Limits on the positions and exposures the order would leave behind
+    fn check_order_impact(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        self.check_position_limits(order, positions)
+            .and_then(|_| self.check_exposure_limits(order, positions))
+            .and_then(|_| self.check_concentration_limits(order, positions))
+            .and_then(|_| self.check_group_limits(order, positions))
+            .and_then(|_| self.check_greek_limits(order, positions))
+            .and_then(|_| self.check_hierarchy_limits(order, positions))
+            .and_then(|_| self.check_buying_power(order, positions))
+    }
+
+    fn check_hierarchy_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let mark = |symbol: &str| self.symbol_mark(symbol).unwrap_or(order.price);
+        let breach = match self.limit_hierarchy.check_order(order, positions, &mark) {
+            Some(breach) => breach,
+            None => return Ok(()),
+        };
+
+        console_log!("Limit node {} breached by order {}", breach.node, order.id);
+        let symbol = order.symbol.clone();
+        let (limit, attempted) = (breach.limit, breach.attempted);
+        Err(match breach.kind {
+            HierarchyLimitKind::OrderNotional => RiskRejection::HierarchyOrderNotional { symbol, limit, attempted },
+            HierarchyLimitKind::Gross => RiskRejection::HierarchyGrossExposure { symbol, limit, attempted },
+            HierarchyLimitKind::Net => RiskRejection::HierarchyNetExposure { symbol, limit, attempted },
+        })
+    }
+
+    fn symbol_mark(&self, symbol: &str) -> Option<f64> {
+        self.pre_trade_controls.reference_prices.get(symbol)
+            .map(|reference| reference.mid_price)
+            .filter(|&price| price > 0.0)
+    }
+
+    // The single booking path for fills: position, cash and limit nodes move
+    // together. Returns the PnL realized by the fill, before `fee`.
+    pub fn apply_fill(&mut self, positions: &mut HashMap<String, Position>, order: &Order, fee: f64) -> f64 {
+        let (instrument, greeks) = self.order_instrument(order, positions);
+        let quantity_change = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+        let position = positions.entry(order.symbol.clone())
+            .or_insert_with(|| Position::new(&order.symbol, instrument, greeks));
+        if position.quantity * quantity_change < 0.0 {
+            self.fill_pending_flatten(&order.symbol, order.quantity);
+        }
+        let realized_pnl = position.apply_fill(quantity_change, order.price, fee);
+
+        self.limit_hierarchy.book_fill(order);
+        self.margin_account.apply_fill(order);
+        self.margin_account.adjust_cash(-fee);
+        realized_pnl
+    }
+
+    // Replaces the limits on one node only; the rest of the tree is untouched
+    pub fn set_hierarchy_limits(&mut self, level: LimitLevel, account: &str, strategy: &str, symbol: &str, limits: NodeLimits) {
+        let path = LimitHierarchy::node_path(level, account, strategy, symbol);
+        let values = [
+            ("max_gross_notional", limits.max_gross_notional),
+            ("max_net_notional", limits.max_net_notional),
+            ("max_order_notional", limits.max_order_notional),
+        ];
+        self.limit_hierarchy.set_limits(&path, level, limits);
+        self.record_limit_change(None, format!("Limit node {}", path), &values);
+    }
+
+    pub fn get_limit_utilization_by_node(&self, positions: &HashMap<String, Position>) -> Vec<NodeUtilization> {
+        let mark = |symbol: &str| self.symbol_mark(symbol).unwrap_or(0.0);
+        self.limit_hierarchy.utilization(positions, &mark)
+    }
+
+    // The kill switch and the reduce-only rungs of the ladder only let exposure shrink
+    fn check_risk_response(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        if self.order_reduces_position(order, positions) {
+            return Ok(());
+        }
+
+        if self.kill_switch.engaged {
+            return Err(RiskRejection::KillSwitch {
+                symbol: order.symbol.clone(),
+                limit: 0.0,
+                attempted: 1.0,
+            });
+        }
+
+        let ladder = &self.response_ladder;
+        if ladder.current_response >= RiskResponse::ReduceOnly {
+            let threshold = ladder.steps.iter()
+                .find(|step| step.response == RiskResponse::ReduceOnly)
+                .map(|step| step.utilization)
+                .unwrap_or(0.0);
+            return Err(RiskRejection::ReduceOnly {
+                symbol: order.symbol.clone(),
+                limit: threshold,
+                attempted: ladder.current_utilization,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Once the daily loss or drawdown limit is hit only position-reducing orders pass
+    fn check_loss_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        if self.order_reduces_position(order, positions) {
+            return Ok(());
+        }
+
+        let monitor = &self.drawdown_monitor;
+        if -monitor.daily_pnl > self.config.max_daily_loss {
+            return Err(RiskRejection::DailyLoss {
+                symbol: order.symbol.clone(),
+                limit: self.config.max_daily_loss,
+                attempted: -monitor.daily_pnl,
+            });
+        }
+
+        if monitor.current_drawdown > monitor.max_allowed_drawdown {
+            return Err(RiskRejection::Drawdown {
+                symbol: order.symbol.clone(),
+                limit: monitor.max_allowed_drawdown,
+                attempted: monitor.current_drawdown,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn order_reduces_position(&self, order: &Order, positions: &HashMap<String, Position>) -> bool {
+        let current_quantity = positions.get(&order.symbol).map(|pos| pos.quantity).unwrap_or(0.0);
+        let new_quantity = match order.side {
+            OrderSide::Buy => current_quantity + order.quantity,
+            OrderSide::Sell => current_quantity - order.quantity,
+        };
+
+        new_quantity.abs() < current_quantity.abs() && new_quantity * current_quantity >= 0.0
+    }
+
+    fn check_fat_finger_limits(&self, order: &Order) -> Result<(), RiskRejection> {
+        let controls = &self.pre_trade_controls;
+        let (max_quantity, max_notional) = controls.symbol_order_limits.get(&order.symbol)
+            .map(|limits| (limits.max_order_quantity, limits.max_order_notional))
+            .unwrap_or((controls.default_max_order_quantity, controls.default_max_order_notional));
+
+        if order.quantity > max_quantity {
+            return Err(RiskRejection::OrderQuantity {
+                symbol: order.symbol.clone(),
+                limit: max_quantity,
+                attempted: order.quantity,
+            });
+        }
+
+        let notional = order.quantity * order.price;
+        if notional > max_notional {
+            return Err(RiskRejection::OrderNotional {
+                symbol: order.symbol.clone(),
+                limit: max_notional,
+                attempted: notional,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_price_collar(&self, order: &Order) -> Result<(), RiskRejection> {
+        // Market orders carry no limit price to collar
+        if matches!(order.order_type, OrderType::Market) {
+            return Ok(());
+        }
+
+        let controls = &self.pre_trade_controls;
+        let reference = match controls.reference_prices.get(&order.symbol) {
+            Some(reference) => match controls.collar_reference {
+                CollarReference::Mid => reference.mid_price,
+                CollarReference::LastTrade => reference.last_trade,
+            },
+            None => return Ok(()),
+        };
+
+        if reference <= 0.0 {
+            return Ok(());
+        }
+
+        let deviation = (order.price - reference).abs() / reference;
+        if deviation > controls.price_collar_pct {
+            return Err(RiskRejection::PriceCollar {
+                symbol: order.symbol.clone(),
+                limit: controls.price_collar_pct,
+                attempted: deviation,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_order_rate(&self, order: &Order) -> Result<(), RiskRejection> {
+        let controls = &self.pre_trade_controls;
+        let window_seconds = controls.throttle_window_ms / 1000.0;
+
+        let orders_in_window = controls.order_timestamps.len() + 1;
+        if orders_in_window as f64 > controls.max_orders_per_second as f64 * window_seconds {
+            return Err(RiskRejection::OrderRate {
+                symbol: order.symbol.clone(),
+                limit: controls.max_orders_per_second as f64,
+                attempted: orders_in_window as f64 / window_seconds,
+            });
+        }
+
+        let messages_in_window = controls.message_timestamps.len() + 1;
+        if messages_in_window as f64 > controls.max_messages_per_second as f64 * window_seconds {
+            return Err(RiskRejection::MessageRate {
+                symbol: order.symbol.clone(),
+                limit: controls.max_messages_per_second as f64,
+                attempted: messages_in_window as f64 / window_seconds,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_duplicate_order(&self, order: &Order) -> Result<(), RiskRejection> {
+        let controls = &self.pre_trade_controls;
+
+        let duplicate = controls.recent_orders.iter().rev().find(|recent| {
+            recent.symbol == order.symbol &&
+            std::mem::discriminant(&recent.side) == std::mem::discriminant(&order.side) &&
+            recent.quantity == order.quantity &&
+            recent.price == order.price &&
+            order.timestamp - recent.timestamp < controls.duplicate_window_ms
+        });
+
+        if let Some(recent) = duplicate {
+            return Err(RiskRejection::DuplicateOrder {
+                symbol: order.symbol.clone(),
+                limit: controls.duplicate_window_ms,
+                attempted: order.timestamp - recent.timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn record_accepted_order(&mut self, order: &Order) {
+        let controls = &mut self.pre_trade_controls;
+        controls.order_timestamps.push_back(order.timestamp);
+        controls.recent_orders.push_back(order.clone());
+    }
+
+    fn prune_throttle_windows(&mut self, timestamp: f64) {
+        let controls = &mut self.pre_trade_controls;
+        let throttle_cutoff = timestamp - controls.throttle_window_ms;
+        let duplicate_cutoff = timestamp - controls.duplicate_window_ms;
+
+        while controls.order_timestamps.front().is_some_and(|&t| t <= throttle_cutoff) {
+            controls.order_timestamps.pop_front();
+        }
+        while controls.message_timestamps.front().is_some_and(|&t| t <= throttle_cutoff) {
+            controls.message_timestamps.pop_front();
+        }
+        while controls.recent_orders.front().is_some_and(|o| o.timestamp <= duplicate_cutoff) {
+            controls.recent_orders.pop_front();
+        }
+    }
+
+    fn check_position_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let notional = order.quantity * order.price;
+        
+        // Check single position limit
+        if notional > self.position_limits.max_single_position {
+            return Err(RiskRejection::SingleOrderNotional {
+                symbol: order.symbol.clone(),
+                limit: self.position_limits.max_single_position,
+                attempted: notional,
+            });
+        }
+
+        // Check if adding this order would exceed position limit for the symbol
+        if let Some(position) = positions.get(&order.symbol) {
+            let new_quantity = match order.side {
+                OrderSide::Buy => position.quantity + order.quantity,
+                OrderSide::Sell => position.quantity - order.quantity,
+            };
+            let new_notional = new_quantity.abs() * order.price;
+            
+            if new_notional > self.config.max_position_size {
+                return Err(RiskRejection::PositionSize {
+                    symbol: order.symbol.clone(),
+                    limit: self.config.max_position_size,
+                    attempted: new_notional,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_exposure_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let order_notional = order.quantity * order.price;
+        let current_gross_exposure = self.calculate_gross_exposure(positions);
+        
+        // Check if adding this order would exceed gross exposure limit
+        if current_gross_exposure + order_notional > self.exposure_limits.gross_exposure_limit {
+            return Err(RiskRejection::GrossExposure {
+                symbol: order.symbol.clone(),
+                limit: self.exposure_limits.gross_exposure_limit,
+                attempted: current_gross_exposure + order_notional,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_concentration_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let order_notional = order.quantity * order.price;
+        let total_portfolio_value = self.calculate_gross_exposure(positions);
+        
+        if total_portfolio_value > 0.0 {
+            let concentration = order_notional / total_portfolio_value;
+            if concentration > self.config.concentration_limit {
+                return Err(RiskRejection::Concentration {
+                    symbol: order.symbol.clone(),
+                    limit: self.config.concentration_limit,
+                    attempted: concentration,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Like the Greek limits, a total already over its limit only rejects orders
+    // that push it further out, so the desk can always trade back inside
+    fn check_group_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let current = self.signed_exposures(positions);
+        let mut exposures = current.clone();
+        let order_exposure = match order.side {
+            OrderSide::Buy => order.quantity * order.price,
+            OrderSide::Sell => -order.quantity * order.price,
+        };
+        *exposures.entry(order.symbol.clone()).or_insert(0.0) += order_exposure;
+
+        let current_net: f64 = current.values().sum();
+        let net_exposure: f64 = exposures.values().sum();
+        if net_exposure.abs() > self.position_limits.max_net_notional && net_exposure.abs() > current_net.abs() {
+            return Err(RiskRejection::NetNotional {
+                symbol: order.symbol.clone(),
+                limit: self.position_limits.max_net_notional,
+                attempted: net_exposure.abs(),
+            });
+        }
+
+        for group in self.symbol_groups(&order.symbol) {
+            let (gross, net) = self.group_totals(&group, &exposures);
+            let (current_gross, current_net) = self.group_totals(&group, &current);
+            let limit = self.group_limit(&group);
+
+            if gross > limit.max_gross && gross > current_gross {
+                console_log!("Group {} gross exposure {:.2} over limit {:.2}", group, gross, limit.max_gross);
+                return Err(RiskRejection::GroupGrossExposure {
+                    symbol: order.symbol.clone(),
+                    limit: limit.max_gross,
+                    attempted: gross,
+                });
+            }
+
+            if net.abs() > limit.max_net && net.abs() > current_net.abs() {
+                console_log!("Group {} net exposure {:.2} over limit {:.2}", group, net, limit.max_net);
+                return Err(RiskRejection::GroupNetExposure {
+                    symbol: order.symbol.clone(),
+                    limit: limit.max_net,
+                    attempted: net.abs(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn group_key(kind: &GroupKind, name: &str) -> String {
+        match kind {
+            GroupKind::Sector => format!("sector:{}", name),
+            GroupKind::Index => format!("index:{}", name),
+            GroupKind::Underlying => format!("underlying:{}", name),
+        }
+    }
+
+    // Group keys a symbol belongs to; unclassified symbols belong to no group
+    fn symbol_groups(&self, symbol: &str) -> Vec<String> {
+        let classification = match self.group_limits.classifications.get(symbol) {
+            Some(classification) => classification,
+            None => return Vec::new(),
+        };
+
+        let mut groups = Vec::new();
+        if !classification.sector.is_empty() {
+            groups.push(Self::group_key(&GroupKind::Sector, &classification.sector));
+        }
+        for index in &classification.indices {
+            groups.push(Self::group_key(&GroupKind::Index, index));
+        }
+        if !classification.underlying.is_empty() {
+            groups.push(Self::group_key(&GroupKind::Underlying, &classification.underlying));
+        }
+        groups
+    }
+
+    fn group_totals(&self, group: &str, exposures: &HashMap<String, f64>) -> (f64, f64) {
+        exposures.iter()
+            .filter(|(symbol, _)| self.symbol_groups(symbol).iter().any(|g| g == group))
+            .fold((0.0, 0.0), |(gross, net), (_, exposure)| (gross + exposure.abs(), net + exposure))
+    }
+
+    fn group_limit(&self, group: &str) -> GroupLimit {
+        if let Some(limit) = self.group_limits.limits.get(group) {
+            return limit.clone();
+        }
+
+        // Sectors without an explicit limit fall back to the firm-wide sector cap
+        if group.starts_with("sector:") {
+            GroupLimit { max_gross: self.position_limits.max_sector_exposure, max_net: f64::INFINITY }
+        } else {
+            GroupLimit { max_gross: f64::INFINITY, max_net: f64::INFINITY }
+        }
+    }
+
+    fn calculate_group_exposures(&self, positions: &HashMap<String, Position>) -> Vec<GroupExposure> {
+        let exposures = self.signed_exposures(positions);
+        let mut groups: Vec<String> = exposures.keys()
+            .flat_map(|symbol| self.symbol_groups(symbol))
+            .collect();
+        groups.sort();
+        groups.dedup();
+
+        groups.into_iter()
+            .map(|group| {
+                let (gross_exposure, net_exposure) = self.group_totals(&group, &exposures);
+                let limit = self.group_limit(&group);
+                let utilization = (gross_exposure / limit.max_gross).max(net_exposure.abs() / limit.max_net);
+                GroupExposure {
+                    group,
+                    gross_exposure,
+                    net_exposure,
+                    gross_limit: limit.max_gross,
+                    net_limit: limit.max_net,
+                    utilization,
+                }
+            })
+            .collect()
+    }
+
+    fn check_greek_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let portfolio = self.calculate_portfolio_greeks(positions);
+        let (instrument, greeks) = self.order_instrument(order, positions);
+
+        let signed_quantity = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+        let order_greeks = self.dollar_greeks(&order.symbol, &instrument, &greeks, signed_quantity, order.price);
+
+        let new_delta = portfolio.delta + order_greeks.delta;
+        if new_delta.abs() > self.exposure_limits.delta_limit && new_delta.abs() > portfolio.delta.abs() {
+            return Err(RiskRejection::DeltaLimit {
+                symbol: order.symbol.clone(),
+                limit: self.exposure_limits.delta_limit,
+                attempted: new_delta.abs(),
+            });
+        }
+
+        let new_gamma = portfolio.gamma + order_greeks.gamma;
+        if new_gamma.abs() > self.exposure_limits.gamma_limit && new_gamma.abs() > portfolio.gamma.abs() {
+            return Err(RiskRejection::GammaLimit {
+                symbol: order.symbol.clone(),
+                limit: self.exposure_limits.gamma_limit,
+                attempted: new_gamma.abs(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Registers or refreshes the instrument definition and per-unit Greeks for a symbol
+    pub fn register_instrument(&mut self, symbol: &str, instrument: InstrumentType, greeks: Greeks) {
+        self.instruments.insert(symbol.to_string(), RegisteredInstrument { instrument, greeks });
+    }
+
+    fn order_instrument(&self, order: &Order, positions: &HashMap<String, Position>) -> (InstrumentType, Greeks) {
+        match positions.get(&order.symbol) {
+            Some(position) => (position.instrument.clone(), position.greeks.clone()),
+            None => self.instruments.get(&order.symbol)
+                .map(|registered| (registered.instrument.clone(), registered.greeks.clone()))
+                .unwrap_or_default(),
         }
     }
 
-    pub fn validate_order(&self, order: &Order, positions: &HashMap<String, Position>) -> bool {
-        // Check position size limits
-        if !self.check_position_limits(order, positions) {
-            console_log!("Order rejected: Position limit exceeded");
-            return false;
-        }
+    // Orders must fit within equity left over after initial margin; orders
+    // that release margin always pass
+    fn check_buying_power(&self, order: &Order, positions: &HashMap<String, Position>) -> Result<(), RiskRejection> {
+        let (instrument, _) = self.order_instrument(order, positions);
+        let current_quantity = positions.get(&order.symbol).map(|pos| pos.quantity).unwrap_or(0.0);
+        let new_quantity = match order.side {
+            OrderSide::Buy => current_quantity + order.quantity,
+            OrderSide::Sell => current_quantity - order.quantity,
+        };
 
-        // Check exposure limits
-        if !self.check_exposure_limits(order, positions) {
-            console_log!("Order rejected: Exposure limit exceeded");
-            return false;
+        let mark = self.symbol_mark(&order.symbol).unwrap_or(order.price);
+        let underlying_price = self.underlying_price(&order.symbol, &instrument, mark);
+        let (current_margin, _) = self.margin_account.requirement(&instrument, current_quantity, mark, underlying_price);
+        let (new_margin, _) = self.margin_account.requirement(&instrument, new_quantity, mark, underlying_price);
+        let additional_margin = new_margin - current_margin;
+        if additional_margin <= 0.0 {
+            return Ok(());
         }
 
-        // Check concentration limits
-        if !self.check_concentration_limits(order, positions) {
-            console_log!("Order rejected: Concentration limit exceeded");
-            return false;
+        let buying_power = self.calculate_margin_status(positions).buying_power;
+        if additional_margin > buying_power {
+            return Err(RiskRejection::BuyingPower {
+                symbol: order.symbol.clone(),
+                limit: buying_power.max(0.0),
+                attempted: additional_margin,
+            });
         }
 
-        true
+        Ok(())
     }
 
-    fn check_position_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> bool {
-        let notional = order.quantity * order.price;
-        
-        // Check single position limit
-        if notional > self.position_limits.max_single_position {
-            return false;
-        }
+    // Cash plus the market value of every holding
+    pub fn account_equity(&self, positions: &HashMap<String, Position>) -> f64 {
+        self.margin_account.cash + positions.values()
+            .map(|pos| pos.quantity * self.mark_price(pos))
+            .sum::<f64>()
+    }
 
-        // Check if adding this order would exceed position limit for the symbol
-        if let Some(position) = positions.get(&order.symbol) {
-            let new_quantity = match order.side {
-                OrderSide::Buy => position.quantity + order.quantity,
-                OrderSide::Sell => position.quantity - order.quantity,
-            };
-            let new_notional = new_quantity.abs() * order.price;
-            
-            if new_notional > self.config.max_position_size {
-                return false;
-            }
+    pub fn calculate_margin_status(&self, positions: &HashMap<String, Position>) -> MarginStatus {
+        let (initial_margin, maintenance_margin) = positions.values()
+            .map(|pos| {
+                let mark = self.mark_price(pos);
+                let underlying_price = self.underlying_price(&pos.symbol, &pos.instrument, mark);
+                self.margin_account.requirement(&pos.instrument, pos.quantity, mark, underlying_price)
+            })
+            .fold((0.0, 0.0), |(initial, maintenance), (i, m)| (initial + i, maintenance + m));
+        let equity = self.account_equity(positions);
+
+        MarginStatus {
+            cash: self.margin_account.cash,
+            equity,
+            initial_margin,
+            maintenance_margin,
+            buying_power: equity - initial_margin,
+            margin_call: self.margin_account.is_margin_call(),
         }
+    }
 
-        true
+    pub fn cash_balance(&self) -> f64 {
+        self.margin_account.cash
     }
 
-    fn check_exposure_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> bool {
-        let order_notional = order.quantity * order.price;
-        let current_gross_exposure = self.calculate_gross_exposure(positions);
-        
-        // Check if adding this order would exceed gross exposure limit
-        if current_gross_exposure + order_notional > self.exposure_limits.gross_exposure_limit {
-            return false;
-        }
+    // Treated as a deposit or withdrawal of the difference
+    pub fn set_cash_balance(&mut self, cash: f64) {
+        self.adjust_cash(cash - self.margin_account.cash);
+    }
 
-        true
+    // Deposits and withdrawals move equity without counting as PnL, so the
+    // peak and day-start baselines move with them
+    pub fn adjust_cash(&mut self, amount: f64) {
+        self.margin_account.adjust_cash(amount);
+        let monitor = &mut self.drawdown_monitor;
+        monitor.current_equity += amount;
+        monitor.peak_equity += amount;
+        monitor.day_start_equity += amount;
+        monitor.intraday_peak_equity += amount;
     }
 
-    fn check_concentration_limits(&self, order: &Order, positions: &HashMap<String, Position>) -> bool {
-        let order_notional = order.quantity * order.price;
-        let total_portfolio_value = self.calculate_gross_exposure(positions);
-        
-        if total_portfolio_value > 0.0 {
-            let concentration = order_notional / total_portfolio_value;
-            if concentration > self.config.concentration_limit {
-                return false;
-            }
+    pub fn set_margin_rules(&mut self, rules: MarginRules) {
+        let values = [
+            ("spot_initial", rules.spot.initial),
+            ("spot_maintenance", rules.spot.maintenance),
+            ("future_initial", rules.future.initial),
+            ("future_maintenance", rules.future.maintenance),
+            ("long_option_initial", rules.long_option.initial),
+            ("long_option_maintenance", rules.long_option.maintenance),
+            ("short_option_initial", rules.short_option.initial),
+            ("short_option_maintenance", rules.short_option.maintenance),
+        ];
+        self.margin_account.set_rules(rules);
+        self.record_limit_change(None, "Margin rules".to_string(), &values);
+    }
+
+    fn underlying_price(&self, symbol: &str, instrument: &InstrumentType, fallback: f64) -> f64 {
+        let underlying = match instrument {
+            InstrumentType::Spot => symbol,
+            InstrumentType::Future { underlying, .. } | InstrumentType::Option { underlying, .. } => underlying.as_str(),
+        };
+
+        self.pre_trade_controls.reference_prices.get(underlying)
+            .map(|reference| reference.mid_price)
+            .filter(|&price| price > 0.0)
+            .unwrap_or(fallback)
+    }
+
+    // Spot and futures are delta-one; options use their supplied per-unit Greeks
+    fn dollar_greeks(&self, symbol: &str, instrument: &InstrumentType, greeks: &Greeks, quantity: f64, price: f64) -> Greeks {
+        let unit = match instrument {
+            InstrumentType::Spot | InstrumentType::Future { .. } => Greeks { delta: 1.0, ..Greeks::default() },
+            InstrumentType::Option { .. } => greeks.clone(),
+        };
+        let underlying_price = self.underlying_price(symbol, instrument, price);
+
+        Greeks {
+            delta: quantity * unit.delta * underlying_price,
+            gamma: quantity * unit.gamma * underlying_price * underlying_price * 0.01,
+            vega: quantity * unit.vega,
+            theta: quantity * unit.theta,
         }
+    }
+
+    pub fn position_greeks(&self, position: &Position) -> Greeks {
+        self.dollar_greeks(&position.symbol, &position.instrument, &position.greeks,
+                           position.quantity, self.mark_price(position))
+    }
+
+    pub fn calculate_portfolio_greeks(&self, positions: &HashMap<String, Position>) -> Greeks {
+        positions.values()
+            .map(|position| self.position_greeks(position))
+            .fold(Greeks::default(), |total, greeks| Greeks {
+                delta: total.delta + greeks.delta,
+                gamma: total.gamma + greeks.gamma,
+                vega: total.vega + greeks.vega,
+                theta: total.theta + greeks.theta,
+            })
+    }
+
+    pub fn set_symbol_classification(&mut self, symbol: &str, classification: SymbolClassification) {
+        self.group_limits.classifications.insert(symbol.to_string(), classification);
+    }
+
+    pub fn set_group_limit(&mut self, kind: GroupKind, name: &str, limit: GroupLimit) {
+        let key = Self::group_key(&kind, name);
+        let values = [("max_gross", limit.max_gross), ("max_net", limit.max_net)];
+        self.group_limits.limits.insert(key.clone(), limit);
+        self.record_limit_change(None, format!("Group limit {}", key), &values);
+    }
 
-        true
+    pub fn get_rejection_counts(&self) -> HashMap<String, u32> {
+        self.rejection_counts.clone()
     }
 
-    pub fn evaluate_risk(&mut self, positions: &HashMap<String, Position>, _quotes: &[Quote]) -> RiskMetrics {
+    pub fn evaluate_risk(&mut self, positions: &HashMap<String, Position>, quotes: &[Quote], order_book: &OrderBook) -> RiskMetrics {
         let gross_exposure = self.calculate_gross_exposure(positions);
         let net_exposure = self.calculate_net_exposure(positions);
         let leverage = self.calculate_leverage(positions);
@@ -185,11 +1411,42 @@ impl RiskManager {
         let var_99 = self.calculate_var(positions, 0.99);
         let expected_shortfall = self.calculate_expected_shortfall(positions, 0.95);
         
+        // Variance-covariance VaR on net signed positions
+        let parametric_var_95 = self.calculate_parametric_var(positions, 0.95);
+        let parametric_var_99 = self.calculate_parametric_var(positions, 0.99);
+        let position_var = self.calculate_position_var(positions, self.var_calculator.confidence_level);
+        let monte_carlo_tail_risk = self.scheduled_monte_carlo_var(positions);
+
         // Calculate max drawdown
         let max_drawdown = self.drawdown_monitor.current_drawdown;
+        let equity = self.drawdown_monitor.current_equity;
+        let daily_pnl = self.drawdown_monitor.daily_pnl;
+        let intraday_drawdown = self.drawdown_monitor.intraday_drawdown;
+        let group_exposures = self.calculate_group_exposures(positions);
+        let portfolio_greeks = self.calculate_portfolio_greeks(positions);
+
+        // Liquidity-adjusted VaR: market VaR plus the cost of walking the book to exit
+        let position_liquidity = self.calculate_position_liquidity(positions, order_book);
+        let total_unwind_cost: f64 = position_liquidity.iter().map(|p| p.unwind_cost.unwrap_or(0.0)).sum();
+        let max_time_to_liquidate_secs = position_liquidity.iter()
+            .map(|p| p.time_to_liquidate_secs)
+            .fold(0.0f64, f64::max);
+        let liquidity_adjusted_var = var_95.max(parametric_var_95) + total_unwind_cost;
+
+        // Worst case if the outstanding quotes were filled
+        let quote_exposures = [QuoteFillScenario::BidsFilled, QuoteFillScenario::AsksFilled, QuoteFillScenario::BothFilled]
+            .iter()
+            .map(|&scenario| self.calculate_quote_exposure(positions, quotes, scenario))
+            .collect();
+        let limit_nodes = self.get_limit_utilization_by_node(positions);
+
+        let mut margin = self.calculate_margin_status(positions);
+        if self.margin_account.update_margin_call(margin.equity, margin.maintenance_margin) {
+            console_log!("Margin call: equity {:.2} below maintenance margin {:.2}", margin.equity, margin.maintenance_margin);
+        }
+        margin.margin_call = self.margin_account.is_margin_call();
         
-        // Calculate overall risk score
-        let risk_score = self.calculate_risk_score(&RiskMetrics {
+        let mut metrics = RiskMetrics {
             var_95,
             var_99,
             expected_shortfall,
@@ -199,19 +1456,219 @@ impl RiskManager {
             leverage,
             concentration_risk,
             risk_score: 0.0, // Will be calculated
-        });
+            equity,
+            daily_pnl,
+            intraday_drawdown,
+            group_exposures,
+            portfolio_greeks,
+            liquidity_adjusted_var,
+            total_unwind_cost,
+            max_time_to_liquidate_secs,
+            position_liquidity,
+            quote_exposures,
+            limit_nodes,
+            margin,
+            parametric_var_95,
+            parametric_var_99,
+            position_var,
+            monte_carlo_tail_risk,
+        };
 
-        RiskMetrics {
-            var_95,
-            var_99,
-            expected_shortfall,
-            max_drawdown,
+        // Calculate overall risk score
+        metrics.risk_score = self.calculate_risk_score(&metrics);
+        metrics
+    }
+
+    // Signed exposures after the quotes on the scenario's side(s) fill at their quoted prices
+    fn quote_fill_exposures(&self, positions: &HashMap<String, Position>, quotes: &[Quote], scenario: QuoteFillScenario) -> HashMap<String, f64> {
+        let mut exposures = self.signed_exposures(positions);
+
+        for quote in quotes {
+            let mut change = 0.0;
+            if scenario != QuoteFillScenario::AsksFilled {
+                change += quote.bid_quantity * quote.bid_price;
+            }
+            if scenario != QuoteFillScenario::BidsFilled {
+                change -= quote.ask_quantity * quote.ask_price;
+            }
+            *exposures.entry(quote.symbol.clone()).or_insert(0.0) += change;
+        }
+
+        exposures
+    }
+
+    fn calculate_quote_exposure(&self, positions: &HashMap<String, Position>, quotes: &[Quote], scenario: QuoteFillScenario) -> QuoteExposure {
+        let exposures = self.quote_fill_exposures(positions, quotes, scenario);
+        let gross_exposure: f64 = exposures.values().map(|e| e.abs()).sum();
+        let net_exposure: f64 = exposures.values().sum();
+        let largest_position = exposures.values().map(|e| e.abs()).fold(0.0f64, f64::max);
+        let concentration = if gross_exposure > 0.0 { largest_position / gross_exposure } else { 0.0 };
+        let var = self.exposure_var(&exposures, 0.95);
+
+        QuoteExposure {
+            scenario,
             gross_exposure,
             net_exposure,
-            leverage,
-            concentration_risk,
-            risk_score,
+            concentration,
+            var,
+            breached_limits: self.check_portfolio_limits(&exposures, 0.0, var),
+        }
+    }
+
+    // Shrinks quote sizes so that no single side, if fully filled, would take a
+    // symbol past its position limit or the portfolio past its gross, net or VaR
+    // limits, and so that each side on its own passes the pre-trade checks
+    // validate_order applies. Sides with no headroom are pulled; quotes with
+    // neither side left are dropped.
+    pub fn trim_quotes(&self, mut quotes: Vec<Quote>, positions: &HashMap<String, Position>) -> Vec<Quote> {
+        for quote in quotes.iter_mut() {
+            let quantity = positions.get(&quote.symbol).map(|pos| pos.quantity).unwrap_or(0.0);
+            if quote.bid_price > 0.0 {
+                let headroom = self.config.max_position_size / quote.bid_price - quantity;
+                quote.bid_quantity = quote.bid_quantity.min(headroom.max(0.0));
+            }
+            if quote.ask_price > 0.0 {
+                let headroom = self.config.max_position_size / quote.ask_price + quantity;
+                quote.ask_quantity = quote.ask_quantity.min(headroom.max(0.0));
+            }
+        }
+
+        let base = self.signed_exposures(positions);
+        let base_gross: f64 = base.values().map(|e| e.abs()).sum();
+        let base_net: f64 = base.values().sum();
+        let base_var = self.exposure_var(&base, 0.95);
+
+        for scenario in [QuoteFillScenario::BidsFilled, QuoteFillScenario::AsksFilled] {
+            let exposure = self.calculate_quote_exposure(positions, &quotes, scenario);
+
+            // Each metric moves roughly linearly in the quoted size, so scale the
+            // side by the fraction of the move that still fits under the limit
+            let scale = [
+                (base_gross, exposure.gross_exposure, self.exposure_limits.gross_exposure_limit),
+                (base_net.abs(), exposure.net_exposure.abs(), self.position_limits.max_net_notional),
+                (base_var, exposure.var, self.config.var_limit),
+            ]
+            .iter()
+            .filter(|(base, stressed, limit)| stressed > limit && stressed > base)
+            .map(|(base, stressed, limit)| ((limit - base) / (stressed - base)).clamp(0.0, 1.0))
+            .fold(1.0f64, f64::min);
+
+            if scale < 1.0 {
+                console_log!("Trimming {:?} quotes to {:.0}% of size", scenario, scale * 100.0);
+                for quote in quotes.iter_mut() {
+                    match scenario {
+                        QuoteFillScenario::BidsFilled => quote.bid_quantity *= scale,
+                        _ => quote.ask_quantity *= scale,
+                    }
+                }
+            }
+        }
+
+        for quote in quotes.iter_mut() {
+            quote.bid_quantity = self.largest_passing_size(quote, OrderSide::Buy, positions);
+            quote.ask_quantity = self.largest_passing_size(quote, OrderSide::Sell, positions);
+        }
+
+        quotes.retain(|quote| quote.bid_quantity > 0.0 || quote.ask_quantity > 0.0);
+        quotes
+    }
+
+    // Largest size up to the quoted one at which the side, sent as an order,
+    // passes the order and impact checks. The limits only tighten with size, so
+    // a failing side is bisected down.
+    fn largest_passing_size(&self, quote: &Quote, side: OrderSide, positions: &HashMap<String, Position>) -> f64 {
+        let (price, quoted) = match side {
+            OrderSide::Buy => (quote.bid_price, quote.bid_quantity),
+            OrderSide::Sell => (quote.ask_price, quote.ask_quantity),
+        };
+        if price <= 0.0 || quoted <= 0.0 {
+            return 0.0;
+        }
+
+        let passes = |quantity: f64| {
+            let order = Order {
+                id: format!("quote-{}", quote.symbol),
+                symbol: quote.symbol.clone(),
+                side: side.clone(),
+                quantity,
+                price,
+                timestamp: quote.timestamp,
+                order_type: OrderType::Limit,
+                account: String::new(),
+                strategy: String::new(),
+            };
+            self.check_order_terms(&order, positions)
+                .and_then(|_| self.check_order_impact(&order, positions))
+                .is_ok()
+        };
+        if passes(quoted) {
+            return quoted;
+        }
+
+        let (mut passing, mut failing) = (0.0, quoted);
+        for _ in 0..QUOTE_SIZE_BISECTIONS {
+            let mid = 0.5 * (passing + failing);
+            if passes(mid) {
+                passing = mid;
+            } else {
+                failing = mid;
+            }
+        }
+        passing
+    }
+
+    fn calculate_position_liquidity(&self, positions: &HashMap<String, Position>, order_book: &OrderBook) -> Vec<PositionLiquidity> {
+        positions.values()
+            .filter(|pos| pos.quantity != 0.0)
+            .map(|pos| {
+                let exit_side = if pos.quantity > 0.0 { OrderSide::Sell } else { OrderSide::Buy };
+                let estimate = order_book.estimate_unwind_cost(
+                    &pos.symbol,
+                    &exit_side,
+                    pos.quantity.abs(),
+                    self.mark_price(pos),
+                    self.recent_volume(&pos.symbol),
+                );
+
+                PositionLiquidity {
+                    symbol: pos.symbol.clone(),
+                    quantity: pos.quantity,
+                    unwind_cost: estimate.as_ref().map(|e| e.cost),
+                    unfilled_quantity: estimate.map_or(pos.quantity.abs(), |e| e.unfilled_quantity),
+                    time_to_liquidate_secs: self.time_to_liquidate(&pos.symbol, pos.quantity.abs()),
+                }
+            })
+            .collect()
+    }
+
+    // Seconds to exit `quantity` trading at the configured share of observed volume
+    pub fn time_to_liquidate(&self, symbol: &str, quantity: f64) -> f64 {
+        let monitor = &self.liquidity_monitor;
+        let volumes = match monitor.traded_volume.get(symbol) {
+            Some(volumes) if volumes.len() >= 2 => volumes,
+            _ => return f64::INFINITY,
+        };
+
+        // The first sample's volume traded before the window opened, so only the
+        // later samples count against the elapsed time
+        let elapsed_secs = (volumes.back().unwrap().0 - volumes.front().unwrap().0) / 1000.0;
+        let total_volume: f64 = volumes.iter().skip(1).map(|&(_, volume)| volume).sum();
+        if elapsed_secs <= 0.0 || total_volume <= 0.0 {
+            return f64::INFINITY;
         }
+
+        let volume_per_sec = total_volume / elapsed_secs;
+        quantity / (volume_per_sec * monitor.participation_rate)
+    }
+
+    // Volume traded in the liquidity window
+    fn recent_volume(&self, symbol: &str) -> f64 {
+        self.liquidity_monitor.traded_volume.get(symbol)
+            .map_or(0.0, |volumes| volumes.iter().map(|&(_, volume)| volume).sum())
+    }
+
+    pub fn set_liquidity_participation_rate(&mut self, participation_rate: f64) {
+        self.liquidity_monitor.participation_rate = participation_rate.clamp(0.001, 1.0);
     }
 
     fn calculate_gross_exposure(&self, positions: &HashMap<String, Position>) -> f64 {
@@ -228,7 +1685,7 @@ impl RiskManager {
 
     fn calculate_leverage(&self, positions: &HashMap<String, Position>) -> f64 {
         let gross_exposure = self.calculate_gross_exposure(positions);
-        let equity = self.drawdown_monitor.peak_equity + self.drawdown_monitor.daily_pnl;
+        let equity = self.account_equity(positions);
         
         if equity > 0.0 {
             gross_exposure / equity
@@ -290,6 +1747,237 @@ impl RiskManager {
         portfolio_value * avg_tail_return.abs()
     }
 
+    pub fn mark_price(&self, position: &Position) -> f64 {
+        self.pre_trade_controls.reference_prices.get(&position.symbol)
+            .map(|reference| reference.mid_price)
+            .filter(|&price| price > 0.0)
+            .unwrap_or(position.average_price)
+    }
+
+    // Net signed notional per symbol at the current mark
+    pub fn signed_exposures(&self, positions: &HashMap<String, Position>) -> HashMap<String, f64> {
+        positions.values()
+            .map(|pos| (pos.symbol.clone(), pos.quantity * self.mark_price(pos)))
+            .collect()
+    }
+
+    // Exposures laid out in covariance-estimator symbol order
+    fn estimator_exposures(&self, exposures: &HashMap<String, f64>) -> Vec<f64> {
+        self.var_calculator.covariance_estimator.symbols().iter()
+            .map(|symbol| exposures.get(symbol).copied().unwrap_or(0.0))
+            .collect()
+    }
+
+    // Signed exposure per covariance-estimator symbol, plus sigma_p and Sigma * w
+    fn portfolio_variance_terms(&self, exposures: &HashMap<String, f64>) -> Option<(Vec<f64>, f64, Vec<f64>)> {
+        let estimator = &self.var_calculator.covariance_estimator;
+        if !estimator.is_ready() {
+            return None;
+        }
+
+        let exposures = self.estimator_exposures(exposures);
+
+        let n = exposures.len();
+        let sigma_w: Vec<f64> = (0..n)
+            .map(|i| (0..n).map(|j| estimator.covariance(i, j) * exposures[j]).sum())
+            .collect();
+
+        let variance: f64 = exposures.iter().zip(&sigma_w).map(|(w, sw)| w * sw).sum();
+        if variance <= 0.0 {
+            return None;
+        }
+
+        Some((exposures, variance.sqrt(), sigma_w))
+    }
+
+    // Covariance is per sampling interval; VaR horizons are in trading days
+    fn horizon_intervals(&self, horizon_days: f64) -> f64 {
+        self.var_calculator.covariance_estimator.intervals_in(horizon_days * TRADING_SECONDS_PER_DAY)
+    }
+
+    fn calculate_parametric_var(&self, positions: &HashMap<String, Position>, confidence_level: f64) -> f64 {
+        self.exposure_var(&self.signed_exposures(positions), confidence_level)
+    }
+
+    // Variance-covariance VaR for an arbitrary set of signed exposures
+    pub fn exposure_var(&self, exposures: &HashMap<String, f64>, confidence_level: f64) -> f64 {
+        match self.portfolio_variance_terms(exposures) {
+            Some((_, sigma_p, _)) => {
+                normal_quantile(confidence_level) * sigma_p * self.horizon_intervals(self.var_calculator.time_horizon_days).sqrt()
+            }
+            None => 0.0,
+        }
+    }
+
+    // Marginal VaR is dVaR/dw_i; component VaR is w_i * marginal and sums to total VaR
+    fn calculate_position_var(&self, positions: &HashMap<String, Position>, confidence_level: f64) -> Vec<PositionVar> {
+        let (exposures, sigma_p, sigma_w) = match self.portfolio_variance_terms(&self.signed_exposures(positions)) {
+            Some(terms) => terms,
+            None => return Vec::new(),
+        };
+
+        let scale = normal_quantile(confidence_level) * self.horizon_intervals(self.var_calculator.time_horizon_days).sqrt();
+        let total_var = scale * sigma_p;
+
+        self.var_calculator.covariance_estimator.symbols().iter().enumerate()
+            .filter(|(i, _)| exposures[*i] != 0.0)
+            .map(|(i, symbol)| {
+                let marginal_var = scale * sigma_w[i] / sigma_p;
+                let component_var = exposures[i] * marginal_var;
+                PositionVar {
+                    symbol: symbol.clone(),
+                    exposure: exposures[i],
+                    marginal_var,
+                    component_var,
+                    contribution_pct: component_var / total_var,
+                }
+            })
+            .collect()
+    }
+
+    // Simulates correlated horizon returns from the estimated covariance and
+    // revalues net positions on each path
+    fn calculate_monte_carlo_var(&self, positions: &HashMap<String, Position>, confidence_levels: &[f64]) -> Vec<TailRiskEstimate> {
+        let estimator = &self.var_calculator.covariance_estimator;
+        let config = &self.var_calculator.monte_carlo;
+        if !estimator.is_ready() || config.paths == 0 {
+            return Vec::new();
+        }
+
+        let exposures = self.estimator_exposures(&self.signed_exposures(positions));
+
+        if exposures.iter().all(|&w| w == 0.0) {
+            return Vec::new();
+        }
+
+        let lower = cholesky_decomposition(&estimator.covariance_matrix());
+        let horizon_scale = self.horizon_intervals(config.horizon_days).sqrt();
+        let mut rng = SimulationRng::new(config.seed);
+        let n = exposures.len();
+
+        let mut losses: Vec<f64> = (0..config.paths)
+            .map(|_| {
+                let shocks: Vec<f64> = (0..n).map(|_| rng.next_normal()).collect();
+
+                // Multivariate t: one chi-squared draw scales every component,
+                // rescaled to unit variance so the covariance is preserved
+                let scale = match config.distribution {
+                    ReturnDistribution::Normal => 1.0,
+                    ReturnDistribution::StudentT { degrees_of_freedom } => {
+                        let chi_squared = rng.next_chi_squared(degrees_of_freedom);
+                        ((degrees_of_freedom - 2.0).max(1e-6) / chi_squared).sqrt()
+                    }
+                };
+
+                let pnl: f64 = (0..n)
+                    .map(|i| {
+                        let correlated: f64 = (0..=i).map(|k| lower[i][k] * shocks[k]).sum();
+                        let simulated_return = correlated * scale * horizon_scale;
+                        exposures[i] * (simulated_return.exp() - 1.0)
+                    })
+                    .sum();
+                -pnl
+            })
+            .collect();
+
+        losses.sort_by(|a, b| b.total_cmp(a));
+
+        confidence_levels.iter()
+            .map(|&confidence_level| {
+                let tail_count = (((1.0 - confidence_level) * losses.len() as f64).ceil() as usize)
+                    .clamp(1, losses.len());
+                let tail = &losses[..tail_count];
+                TailRiskEstimate {
+                    confidence_level,
+                    var: tail[tail_count - 1].max(0.0),
+                    expected_shortfall: (tail.iter().sum::<f64>() / tail_count as f64).max(0.0),
+                }
+            })
+            .collect()
+    }
+
+    pub fn run_monte_carlo_var(&self, positions: &HashMap<String, Position>, confidence_levels: &[f64]) -> Vec<TailRiskEstimate> {
+        self.calculate_monte_carlo_var(positions, confidence_levels)
+    }
+
+    // Reruns the simulation when the refresh interval has passed and returns
+    // the latest result otherwise
+    fn scheduled_monte_carlo_var(&mut self, positions: &HashMap<String, Position>) -> Vec<TailRiskEstimate> {
+        let config = &self.var_calculator.monte_carlo;
+        let due = config.last_run_time
+            .is_none_or(|last| self.current_time - last >= config.refresh_interval_ms);
+        if due {
+            self.refresh_monte_carlo_var(positions);
+        }
+        self.var_calculator.monte_carlo.last_result.clone()
+    }
+
+    // Runs the simulation now with the configured confidence levels
+    pub fn refresh_monte_carlo_var(&mut self, positions: &HashMap<String, Position>) -> Vec<TailRiskEstimate> {
+        let result = self.calculate_monte_carlo_var(positions, &self.var_calculator.confidence_levels);
+        let config = &mut self.var_calculator.monte_carlo;
+        config.last_run_time = Some(self.current_time);
+        config.last_result = result.clone();
+        result
+    }
+
+    // Rejects a non-positive or non-finite horizon and Student-t degrees of
+    // freedom that are not finite and above 2 (the variance must exist)
+    pub fn set_monte_carlo_config(&mut self, paths: usize, horizon_days: f64, distribution: ReturnDistribution,
+                                  seed: u64, refresh_interval_ms: f64) -> Result<(), String> {
+        if !(horizon_days > 0.0 && horizon_days.is_finite()) {
+            return Err(format!("Monte Carlo horizon must be positive, got {}", horizon_days));
+        }
+        if let ReturnDistribution::StudentT { degrees_of_freedom } = distribution {
+            if !(degrees_of_freedom > 2.0 && degrees_of_freedom.is_finite()) {
+                return Err(format!("Student-t degrees of freedom must be finite and above 2, got {}", degrees_of_freedom));
+            }
+        }
+
+        self.var_calculator.monte_carlo = MonteCarloConfig {
+            paths,
+            horizon_days,
+            distribution,
+            seed,
+            refresh_interval_ms: refresh_interval_ms.max(0.0),
+            last_run_time: None,
+            last_result: Vec::new(),
+        };
+        self.record_limit_change(None, "Monte Carlo VaR".to_string(), &[
+            ("paths", paths as f64),
+            ("horizon_days", horizon_days),
+            ("refresh_interval_ms", refresh_interval_ms),
+        ]);
+        Ok(())
+    }
+
+    pub fn set_confidence_levels(&mut self, confidence_levels: Vec<f64>) {
+        self.var_calculator.confidence_levels = confidence_levels.into_iter()
+            .filter(|&level| level > 0.0 && level < 1.0)
+            .collect();
+        self.var_calculator.monte_carlo.last_run_time = None;
+    }
+
+    pub fn get_correlation_matrix(&self) -> HashMap<String, HashMap<String, f64>> {
+        self.var_calculator.correlation_matrix.clone()
+    }
+
+    pub fn get_covariance_snapshot(&self) -> CovarianceSnapshot {
+        self.var_calculator.covariance_estimator.snapshot()
+    }
+
+    pub fn set_covariance_config(&mut self, config: CovarianceConfig) {
+        self.var_calculator.covariance_estimator.set_config(config);
+    }
+
+    pub fn refit_covariance_models(&mut self) -> usize {
+        self.var_calculator.covariance_estimator.refit_asset_variances()
+    }
+
+    pub fn minimum_variance_hedge_ratio(&self, symbol: &str, hedge: &str) -> Option<f64> {
+        self.var_calculator.covariance_estimator.minimum_variance_hedge_ratio(symbol, hedge)
+    }
+
     pub fn update_returns(&mut self, portfolio_return: f64) {
         self.var_calculator.historical_returns.push_back(portfolio_return);
         
@@ -298,11 +1986,17 @@ impl RiskManager {
         }
     }
 
+    // VaR the limits act on. Historical VaR needs a fed return history, so the
+    // covariance-based parametric VaR keeps the limit live without one.
+    fn limit_var(metrics: &RiskMetrics) -> f64 {
+        metrics.var_95.max(metrics.parametric_var_95)
+    }
+
     fn calculate_risk_score(&self, metrics: &RiskMetrics) -> f64 {
         let mut score = 0.0;
 
         // VaR component (0-25 points)
-        score += (metrics.var_95 / self.config.var_limit * 25.0).min(25.0);
+        score += (Self::limit_var(metrics) / self.config.var_limit * 25.0).min(25.0);
 
         // Drawdown component (0-20 points)
         score += (metrics.max_drawdown / self.drawdown_monitor.max_allowed_drawdown * 20.0).min(20.0);
@@ -319,14 +2013,352 @@ impl RiskManager {
         score.min(100.0)
     }
 
+    // Cash PnL not carried on any position (fees, rebates); folded into equity on the next mark
     pub fn update_daily_pnl(&mut self, pnl_change: f64) {
+        self.margin_account.adjust_cash(pnl_change);
+        self.drawdown_monitor.current_equity += pnl_change;
         self.drawdown_monitor.daily_pnl += pnl_change;
     }
 
+    // Re-marks every position at the latest mid, then rolls equity, peak,
+    // drawdown and daily PnL forward. Call once per tick.
+    pub fn mark_to_market(&mut self, positions: &mut HashMap<String, Position>, timestamp: f64) {
+        for position in positions.values_mut() {
+            if let Some(registered) = self.instruments.get(&position.symbol) {
+                position.instrument = registered.instrument.clone();
+                position.greeks = registered.greeks.clone();
+            }
+
+            let mark = self.mark_price(position);
+            position.unrealized_pnl = if position.quantity != 0.0 {
+                (mark - position.average_price) * position.quantity
+            } else {
+                0.0
+            };
+        }
+
+        let equity = self.account_equity(positions);
+        let monitor = &mut self.drawdown_monitor;
+        monitor.current_equity = equity;
+
+        let trading_day = trading_day_index(timestamp, monitor.rollover_offset_ms);
+        if monitor.current_trading_day != Some(trading_day) {
+            if monitor.current_trading_day.is_some() {
+                console_log!("Trading day rollover: previous daily PnL {:.2}", monitor.daily_pnl);
+            }
+            monitor.current_trading_day = Some(trading_day);
+            monitor.day_start_equity = equity;
+            monitor.intraday_peak_equity = equity;
+        }
+
+        monitor.daily_pnl = equity - monitor.day_start_equity;
+        monitor.peak_equity = monitor.peak_equity.max(equity);
+        monitor.intraday_peak_equity = monitor.intraday_peak_equity.max(equity);
+
+        monitor.current_drawdown = if monitor.peak_equity > 0.0 {
+            ((monitor.peak_equity - equity) / monitor.peak_equity).max(0.0)
+        } else {
+            0.0
+        };
+        monitor.intraday_drawdown = if monitor.intraday_peak_equity > 0.0 {
+            ((monitor.intraday_peak_equity - equity) / monitor.intraday_peak_equity).max(0.0)
+        } else {
+            0.0
+        };
+        monitor.max_observed_drawdown = monitor.max_observed_drawdown.max(monitor.current_drawdown);
+    }
+
+    // Trading day boundary as hours after UTC midnight (e.g. 10.0 for a 15:30 IST close)
+    // Only the boundary moves: the current day keeps its accumulated PnL and
+    // loss state until the new boundary is actually crossed
+    pub fn set_trading_day_rollover(&mut self, offset_hours: f64) {
+        let monitor = &mut self.drawdown_monitor;
+        monitor.rollover_offset_ms = offset_hours * 3_600_000.0;
+        if monitor.current_trading_day.is_some() {
+            monitor.current_trading_day = Some(trading_day_index(self.current_time, monitor.rollover_offset_ms));
+        }
+    }
+
+    // Resets a flat account to `equity` in cash, e.g. at the start of a backtest
+    pub fn set_starting_equity(&mut self, equity: f64) {
+        self.margin_account.cash = equity;
+        let monitor = &mut self.drawdown_monitor;
+        monitor.current_equity = equity;
+        monitor.peak_equity = equity;
+        monitor.day_start_equity = equity;
+        monitor.intraday_peak_equity = equity;
+        monitor.daily_pnl = 0.0;
+        monitor.current_drawdown = 0.0;
+        monitor.intraday_drawdown = 0.0;
+        monitor.max_observed_drawdown = 0.0;
+        monitor.current_trading_day = None;
+    }
+
+    pub fn get_max_observed_drawdown(&self) -> f64 {
+        self.drawdown_monitor.max_observed_drawdown
+    }
+
+    // Portfolio limits that would breach for a hypothetical set of signed
+    // exposures after an additional PnL of `pnl` and a VaR of `var`
+    pub fn check_portfolio_limits(&self, exposures: &HashMap<String, f64>, pnl: f64, var: f64) -> Vec<LimitBreach> {
+        let gross_exposure: f64 = exposures.values().map(|e| e.abs()).sum();
+        let net_exposure: f64 = exposures.values().sum();
+        let largest_position = exposures.values().map(|e| e.abs()).fold(0.0f64, f64::max);
+
+        let daily_pnl = self.drawdown_monitor.daily_pnl + pnl;
+        let equity = self.drawdown_monitor.current_equity + pnl;
+        let leverage = if equity > 0.0 { gross_exposure / equity } else { f64::INFINITY };
+        let open_positions = exposures.values().filter(|e| **e != 0.0).count();
+        let concentration = if open_positions > 1 { largest_position / gross_exposure } else { 0.0 };
+        let drawdown = (self.drawdown_monitor.peak_equity - equity) / self.drawdown_monitor.peak_equity;
+
+        let checks = [
+            ("GrossExposure", self.exposure_limits.gross_exposure_limit, gross_exposure),
+            ("NetExposure", self.position_limits.max_net_notional, net_exposure.abs()),
+            ("PositionSize", self.config.max_position_size, largest_position),
+            ("Concentration", self.config.concentration_limit, concentration),
+            ("Leverage", self.config.leverage_limit, leverage),
+            ("Var", self.config.var_limit, var),
+            ("DailyLoss", self.config.max_daily_loss, -daily_pnl),
+            ("Drawdown", self.drawdown_monitor.max_allowed_drawdown, drawdown),
+        ];
+
+        checks.iter()
+            .filter(|(_, limit_value, stressed_value)| stressed_value > limit_value)
+            .map(|(limit, limit_value, stressed_value)| LimitBreach {
+                limit: limit.to_string(),
+                limit_value: *limit_value,
+                stressed_value: *stressed_value,
+            })
+            .collect()
+    }
+
+    // Fraction of each limit in use; the risk score counts as a utilization out of 100
+    fn utilization_components(&self, metrics: &RiskMetrics) -> [(&'static str, f64); 9] {
+        let monitor = &self.drawdown_monitor;
+        let margin_utilization = if metrics.margin.equity > 0.0 {
+            metrics.margin.maintenance_margin / metrics.margin.equity
+        } else if metrics.margin.maintenance_margin > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        };
+        [
+            ("RiskScore", metrics.risk_score / 100.0),
+            ("Var", Self::limit_var(metrics) / self.config.var_limit),
+            ("Leverage", metrics.leverage / self.config.leverage_limit),
+            ("GrossExposure", metrics.gross_exposure / self.exposure_limits.gross_exposure_limit),
+            ("Drawdown", monitor.current_drawdown / monitor.max_allowed_drawdown),
+            ("DailyLoss", -monitor.daily_pnl / self.config.max_daily_loss),
+            ("Delta", metrics.portfolio_greeks.delta.abs() / self.exposure_limits.delta_limit),
+            ("Gamma", metrics.portfolio_greeks.gamma.abs() / self.exposure_limits.gamma_limit),
+            ("Margin", margin_utilization),
+        ]
+    }
+
+    // Highest fraction of any limit in use
+    pub fn limit_utilization(&self, metrics: &RiskMetrics) -> f64 {
+        self.utilization_components(metrics).iter()
+            .map(|&(_, utilization)| utilization)
+            .fold(0.0f64, f64::max)
+    }
+
+    // Picks the ladder rung for the current metrics. Flatten latches the kill switch.
+    pub fn update_risk_response(&mut self, metrics: &RiskMetrics, timestamp: f64) -> RiskResponse {
+        let utilization = self.limit_utilization(metrics);
+
+        // Log each limit as it goes into breach
+        let components = self.utilization_components(metrics);
+        let breached: Vec<String> = components.iter()
+            .filter(|&&(_, utilization)| utilization >= 1.0)
+            .map(|&(name, _)| name.to_string())
+            .collect();
+        for &(name, limit_utilization) in components.iter() {
+            if limit_utilization >= 1.0 && !self.response_ladder.breached_limits.iter().any(|b| b == name) {
+                self.record_event(timestamp, RiskEventType::LimitBreach, None,
+                                  format!("{} limit breached", name), &[
+                    ("utilization", limit_utilization),
+                    ("var_95", Self::limit_var(metrics)),
+                    ("gross_exposure", metrics.gross_exposure),
+                    ("leverage", metrics.leverage),
+                    ("risk_score", metrics.risk_score),
+                ]);
+            }
+        }
+        self.response_ladder.breached_limits = breached;
+
+        let response = if self.kill_switch.engaged {
+            RiskResponse::Flatten
+        } else {
+            self.response_ladder.steps.iter()
+                .filter(|step| utilization >= step.utilization)
+                .map(|step| step.response)
+                .max()
+                .unwrap_or(RiskResponse::Normal)
+        };
+
+        if response != self.response_ladder.current_response {
+            console_log!("Risk response {:?} -> {:?} at utilization {:.2}",
+                        self.response_ladder.current_response, response, utilization);
+            self.record_event(timestamp, RiskEventType::RiskResponse, None,
+                              format!("{:?} -> {:?}", self.response_ladder.current_response, response), &[
+                ("utilization", utilization),
+                ("risk_score", metrics.risk_score),
+            ]);
+        }
+
+        if response == RiskResponse::Flatten && !self.kill_switch.engaged {
+            self.engage_kill_switch(&format!("Automatic flatten at utilization {:.2}", utilization), timestamp);
+        }
+
+        if response != RiskResponse::Flatten {
+            self.response_ladder.pending_flatten.clear();
+        }
+        self.response_ladder.current_response = response;
+        self.response_ladder.current_utilization = utilization;
+        response
+    }
+
+    // Applies the current rung to outgoing quotes
+    pub fn apply_risk_response(&self, quotes: Vec<Quote>, positions: &HashMap<String, Position>) -> Vec<Quote> {
+        let response = self.response_ladder.current_response;
+        if response >= RiskResponse::CancelAll {
+            return Vec::new();
+        }
+
+        quotes.into_iter()
+            .map(|mut quote| {
+                if response >= RiskResponse::WidenSpreads {
+                    let mid = (quote.bid_price + quote.ask_price) / 2.0;
+                    let half_spread = (quote.ask_price - quote.bid_price) / 2.0 * self.response_ladder.spread_widen_factor;
+                    quote.bid_price = mid - half_spread;
+                    quote.ask_price = mid + half_spread;
+                }
+
+                if response >= RiskResponse::ReduceSize {
+                    quote.bid_quantity *= self.response_ladder.size_reduction_factor;
+                    quote.ask_quantity *= self.response_ladder.size_reduction_factor;
+                }
+
+                if response >= RiskResponse::ReduceOnly {
+                    let quantity = positions.get(&quote.symbol).map(|pos| pos.quantity).unwrap_or(0.0);
+                    // Only quote the side that brings inventory back towards flat, capped at the position
+                    quote.bid_quantity = quote.bid_quantity.min((-quantity).max(0.0));
+                    quote.ask_quantity = quote.ask_quantity.min(quantity.max(0.0));
+                }
+
+                quote
+            })
+            .filter(|quote| quote.bid_quantity > 0.0 || quote.ask_quantity > 0.0)
+            .collect()
+    }
+
+    // Market orders that close every open position; empty unless flattening.
+    // Quantity already sent and not yet filled is not sent again, so each tick
+    // only covers what the earlier flatten orders leave open.
+    pub fn flatten_orders(&mut self, positions: &HashMap<String, Position>, timestamp: f64) -> Vec<Order> {
+        if self.response_ladder.current_response != RiskResponse::Flatten {
+            return Vec::new();
+        }
+
+        let mut pending_flatten = std::mem::take(&mut self.response_ladder.pending_flatten);
+        pending_flatten.retain(|symbol, _| positions.get(symbol).is_some_and(|pos| pos.quantity != 0.0));
+
+        let mut orders = Vec::new();
+        for pos in positions.values().filter(|pos| pos.quantity != 0.0) {
+            let open = pos.quantity.abs();
+            let pending = pending_flatten.entry(pos.symbol.clone()).or_insert(0.0);
+            *pending = pending.min(open);
+            let remaining = open - *pending;
+            if remaining < 0.001 {
+                continue;
+            }
+            *pending = open;
+
+            orders.push(Order {
+                id: format!("flatten_{}_{}", pos.symbol, timestamp),
+                symbol: pos.symbol.clone(),
+                side: if pos.quantity > 0.0 { OrderSide::Sell } else { OrderSide::Buy },
+                quantity: remaining,
+                price: self.mark_price(pos),
+                timestamp,
+                order_type: OrderType::Market,
+                account: String::new(),
+                strategy: String::new(),
+            });
+        }
+        self.response_ladder.pending_flatten = pending_flatten;
+        orders
+    }
+
+    // A closing fill works off the flatten quantity outstanding on its symbol
+    fn fill_pending_flatten(&mut self, symbol: &str, quantity: f64) {
+        if let Some(pending) = self.response_ladder.pending_flatten.get_mut(symbol) {
+            *pending -= quantity;
+            if *pending <= 0.0 {
+                self.response_ladder.pending_flatten.remove(symbol);
+            }
+        }
+    }
+
+    // Forgets flatten quantity for a symbol whose flatten order was rejected or
+    // cancelled, so the next tick sends it again
+    pub fn release_flatten(&mut self, symbol: &str) {
+        self.response_ladder.pending_flatten.remove(symbol);
+    }
+
+    pub fn engage_kill_switch(&mut self, reason: &str, timestamp: f64) {
+        console_log!("KILL SWITCH ENGAGED: {}", reason);
+        self.kill_switch = KillSwitch {
+            engaged: true,
+            reason: reason.to_string(),
+            engaged_at: timestamp,
+        };
+        self.response_ladder.current_response = RiskResponse::Flatten;
+        self.record_event(timestamp, RiskEventType::KillSwitch, None, format!("Engaged: {}", reason), &[]);
+    }
+
+    // The kill switch stays latched until this is called explicitly
+    pub fn reset_kill_switch(&mut self) {
+        console_log!("Kill switch reset");
+        self.kill_switch = KillSwitch::default();
+        self.response_ladder.current_response = RiskResponse::Normal;
+        self.response_ladder.pending_flatten.clear();
+        self.record_event(self.current_time, RiskEventType::KillSwitch, None, "Reset".to_string(), &[]);
+    }
+
+    pub fn get_kill_switch(&self) -> KillSwitch {
+        self.kill_switch.clone()
+    }
+
+    pub fn get_risk_response(&self) -> RiskResponse {
+        self.response_ladder.current_response
+    }
+
+    pub fn set_response_ladder(&mut self, mut steps: Vec<ResponseStep>, spread_widen_factor: f64, size_reduction_factor: f64) {
+        steps.sort_by(|a, b| a.utilization.partial_cmp(&b.utilization).unwrap());
+        self.response_ladder.steps = steps;
+        self.response_ladder.spread_widen_factor = spread_widen_factor;
+        self.response_ladder.size_reduction_factor = size_reduction_factor;
+        self.record_limit_change(None, "Risk response ladder".to_string(), &[
+            ("steps", self.response_ladder.steps.len() as f64),
+            ("spread_widen_factor", spread_widen_factor),
+            ("size_reduction_factor", size_reduction_factor),
+        ]);
+    }
+
     pub fn is_risk_limit_breached(&self, metrics: &RiskMetrics) -> bool {
         metrics.risk_score > 80.0 || 
-        metrics.max_drawdown > self.drawdown_monitor.max_allowed_drawdown ||
+        self.drawdown_monitor.current_drawdown > self.drawdown_monitor.max_allowed_drawdown ||
+        -self.drawdown_monitor.daily_pnl > self.config.max_daily_loss ||
         metrics.leverage > self.config.leverage_limit ||
-        metrics.var_95 > self.config.var_limit
+        Self::limit_var(metrics) > self.config.var_limit ||
+        metrics.portfolio_greeks.delta.abs() > self.exposure_limits.delta_limit ||
+        metrics.portfolio_greeks.gamma.abs() > self.exposure_limits.gamma_limit
     }
 }
+
+// Days since the epoch, with each day starting `rollover_offset_ms` after UTC midnight
+fn trading_day_index(timestamp: f64, rollover_offset_ms: f64) -> i64 {
+    ((timestamp - rollover_offset_ms) / 86_400_000.0).floor() as i64
+}
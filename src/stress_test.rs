@@ -0,0 +1,212 @@
+use crate::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct StressTestEngine {
+    scenarios: Vec<StressScenario>,
+    historical_data: Vec<MarketData>,
+    confidence_level: f64,
+}
+
+// Delta-gamma sensitivity of one position to the price it moves with:
+// pnl(x) = delta * x + 0.5 * convexity * x^2 for a fractional move x
+#[derive(Debug, Clone)]
+struct PositionSensitivity {
+    risk_factor: String, // The symbol itself for spot, the underlying otherwise
+    exposure: f64,
+    delta: f64,
+    convexity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StressScenario {
+    pub name: String,
+    pub shock: ScenarioShock,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScenarioShock {
+    // Same percentage move applied to every symbol
+    UniformMove { pct_change: f64 },
+    // Percentage move per symbol; derivatives move with their underlying and
+    // symbols not listed are unchanged
+    SymbolMoves { pct_changes: HashMap<String, f64> },
+    // Scales the estimated return volatility, prices unchanged
+    VolatilityMultiplier { multiplier: f64 },
+    // Replays loaded market data between two timestamps and takes the worst point on the path
+    HistoricalReplay { start_time: f64, end_time: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub scenario: String,
+    pub pnl: f64,
+    pub gross_exposure: f64,
+    pub net_exposure: f64,
+    pub stressed_var: f64,
+    pub symbol_pnl: HashMap<String, f64>,
+    pub breached_limits: Vec<LimitBreach>,
+}
+
+impl Default for StressTestEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StressTestEngine {
+    pub fn new() -> Self {
+        let uniform = |name: &str, pct_change: f64| StressScenario {
+            name: name.to_string(),
+            shock: ScenarioShock::UniformMove { pct_change },
+        };
+        let vol = |name: &str, multiplier: f64| StressScenario {
+            name: name.to_string(),
+            shock: ScenarioShock::VolatilityMultiplier { multiplier },
+        };
+
+        Self {
+            scenarios: vec![
+                uniform("Market -5%", -0.05),
+                uniform("Market -10%", -0.10),
+                uniform("Market -20%", -0.20),
+                uniform("Market +5%", 0.05),
+                uniform("Market +10%", 0.10),
+                vol("Volatility x2", 2.0),
+                vol("Volatility x3", 3.0),
+            ],
+            historical_data: Vec::new(),
+            confidence_level: 0.99,
+        }
+    }
+
+    pub fn add_scenario(&mut self, scenario: StressScenario) {
+        self.scenarios.retain(|s| s.name != scenario.name);
+        self.scenarios.push(scenario);
+    }
+
+    pub fn remove_scenario(&mut self, name: &str) {
+        self.scenarios.retain(|s| s.name != name);
+    }
+
+    pub fn get_scenarios(&self) -> &[StressScenario] {
+        &self.scenarios
+    }
+
+    pub fn load_historical_data(&mut self, mut data: Vec<MarketData>) {
+        data.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        console_log!("Stress engine loaded {} historical ticks", data.len());
+        self.historical_data = data;
+    }
+
+    pub fn run_all(&self, positions: &HashMap<String, Position>, risk_manager: &RiskManager) -> Vec<ScenarioResult> {
+        self.scenarios.iter()
+            .map(|scenario| self.run_scenario(scenario, positions, risk_manager))
+            .collect()
+    }
+
+    pub fn run_scenario(
+        &self,
+        scenario: &StressScenario,
+        positions: &HashMap<String, Position>,
+        risk_manager: &RiskManager,
+    ) -> ScenarioResult {
+        let sensitivities = Self::sensitivities(positions, risk_manager);
+
+        let (price_moves, vol_multiplier) = match &scenario.shock {
+            ScenarioShock::UniformMove { pct_change } => {
+                let moves = sensitivities.values()
+                    .map(|sensitivity| (sensitivity.risk_factor.clone(), *pct_change))
+                    .collect();
+                (moves, 1.0)
+            }
+            ScenarioShock::SymbolMoves { pct_changes } => (pct_changes.clone(), 1.0),
+            ScenarioShock::VolatilityMultiplier { multiplier } => (HashMap::new(), *multiplier),
+            ScenarioShock::HistoricalReplay { start_time, end_time } => {
+                (self.worst_historical_moves(&sensitivities, *start_time, *end_time), 1.0)
+            }
+        };
+
+        let symbol_pnl = Self::revalue(&sensitivities, &price_moves);
+        // Each position is worth its current exposure plus the revaluation PnL
+        let shocked_exposures: HashMap<String, f64> = sensitivities.iter()
+            .map(|(symbol, sensitivity)| (symbol.clone(), sensitivity.exposure + symbol_pnl[symbol]))
+            .collect();
+
+        let pnl: f64 = symbol_pnl.values().sum();
+        let gross_exposure = shocked_exposures.values().map(|e| e.abs()).sum();
+        let net_exposure = shocked_exposures.values().sum();
+        let stressed_var = risk_manager.exposure_var(&shocked_exposures, self.confidence_level) * vol_multiplier;
+        let breached_limits = risk_manager.check_portfolio_limits(&shocked_exposures, pnl, stressed_var);
+
+        ScenarioResult {
+            scenario: scenario.name.clone(),
+            pnl,
+            gross_exposure,
+            net_exposure,
+            stressed_var,
+            symbol_pnl,
+            breached_limits,
+        }
+    }
+
+    // Options take their registered per-unit Greeks; spot and futures are delta-one
+    fn sensitivities(positions: &HashMap<String, Position>, risk_manager: &RiskManager) -> HashMap<String, PositionSensitivity> {
+        positions.values()
+            .map(|pos| {
+                let risk_factor = match &pos.instrument {
+                    InstrumentType::Spot => pos.symbol.clone(),
+                    InstrumentType::Future { underlying, .. } | InstrumentType::Option { underlying, .. } => underlying.clone(),
+                };
+                let greeks = risk_manager.position_greeks(pos);
+                let sensitivity = PositionSensitivity {
+                    risk_factor,
+                    exposure: pos.quantity * risk_manager.mark_price(pos),
+                    delta: greeks.delta,
+                    // Dollar gamma is quoted per 1% move of the underlying
+                    convexity: greeks.gamma * 100.0,
+                };
+                (pos.symbol.clone(), sensitivity)
+            })
+            .collect()
+    }
+
+    // Delta-gamma PnL per position for fractional moves keyed by risk factor
+    fn revalue(sensitivities: &HashMap<String, PositionSensitivity>, price_moves: &HashMap<String, f64>) -> HashMap<String, f64> {
+        sensitivities.iter()
+            .map(|(symbol, sensitivity)| {
+                let pct_change = price_moves.get(&sensitivity.risk_factor).copied().unwrap_or(0.0);
+                let pnl = sensitivity.delta * pct_change + 0.5 * sensitivity.convexity * pct_change * pct_change;
+                (symbol.clone(), pnl)
+            })
+            .collect()
+    }
+
+    // Walks the replay window re-marking each risk factor from its first price in
+    // the window, and returns the per-factor moves at the portfolio's worst point
+    fn worst_historical_moves(&self, sensitivities: &HashMap<String, PositionSensitivity>, start_time: f64, end_time: f64) -> HashMap<String, f64> {
+        let mut start_prices: HashMap<String, f64> = HashMap::new();
+        let mut current_moves: HashMap<String, f64> = HashMap::new();
+        let mut worst_moves = HashMap::new();
+        let mut worst_pnl = 0.0;
+
+        let window = self.historical_data.iter()
+            .filter(|tick| tick.timestamp >= start_time && tick.timestamp <= end_time)
+            .filter(|tick| tick.last_price > 0.0 &&
+                sensitivities.values().any(|sensitivity| sensitivity.risk_factor == tick.symbol));
+
+        for tick in window {
+            let start_price = *start_prices.entry(tick.symbol.clone()).or_insert(tick.last_price);
+            current_moves.insert(tick.symbol.clone(), tick.last_price / start_price - 1.0);
+
+            let pnl: f64 = Self::revalue(sensitivities, &current_moves).values().sum();
+
+            if pnl < worst_pnl {
+                worst_pnl = pnl;
+                worst_moves = current_moves.clone();
+            }
+        }
+
+        worst_moves
+    }
+}
@@ -0,0 +1,115 @@
+use crate::*;
+use std::collections::HashMap;
+
+// Settings a symbol's volatility model is built with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolatilityParameters {
+    pub variance_model: GarchVariant,
+    pub refit_interval: usize,
+    pub fit_window: usize,
+    pub estimator_weights: Vec<EstimatorWeight>,
+    pub bar_interval_secs: f64,
+    pub range_window: usize,
+    pub regime_states: usize,
+}
+
+impl Default for VolatilityParameters {
+    fn default() -> Self {
+        Self {
+            variance_model: GarchVariant::Garch,
+            refit_interval: 250,
+            fit_window: 500,
+            estimator_weights: vec![
+                EstimatorWeight { estimator: VolatilityEstimator::Ewma, weight: 0.6 },
+                EstimatorWeight { estimator: VolatilityEstimator::Garch, weight: 0.4 },
+            ],
+            bar_interval_secs: 60.0,
+            range_window: 30,
+            regime_states: 2,
+        }
+    }
+}
+
+// Independent volatility models keyed by symbol, created on first use so
+// returns are never computed across two instruments' prices
+#[derive(Debug, Clone)]
+pub struct VolatilityRegistry {
+    models: HashMap<String, VolatilityModel>,
+    default_parameters: VolatilityParameters,
+    symbol_parameters: HashMap<String, VolatilityParameters>,
+}
+
+impl Default for VolatilityRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VolatilityRegistry {
+    pub fn new() -> Self {
+        Self {
+            models: HashMap::new(),
+            default_parameters: VolatilityParameters::default(),
+            symbol_parameters: HashMap::new(),
+        }
+    }
+
+    pub fn model_mut(&mut self, symbol: &str) -> &mut VolatilityModel {
+        if !self.models.contains_key(symbol) {
+            let mut model = VolatilityModel::new();
+            model.apply_parameters(&self.parameters(symbol));
+            console_log!("Created volatility model for {}", symbol);
+            self.models.insert(symbol.to_string(), model);
+        }
+        self.models.get_mut(symbol).unwrap()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&VolatilityModel> {
+        self.models.get(symbol)
+    }
+
+    pub fn parameters(&self, symbol: &str) -> VolatilityParameters {
+        self.symbol_parameters.get(symbol)
+            .unwrap_or(&self.default_parameters)
+            .clone()
+    }
+
+    // Overrides a symbol's parameters, applying them to its model if it exists
+    pub fn set_parameters(&mut self, symbol: &str, parameters: VolatilityParameters) {
+        if let Some(model) = self.models.get_mut(symbol) {
+            model.apply_parameters(&parameters);
+        }
+        self.symbol_parameters.insert(symbol.to_string(), parameters);
+    }
+
+    pub fn update_parameters(&mut self, symbol: &str, update: impl FnOnce(&mut VolatilityParameters)) {
+        let mut parameters = self.parameters(symbol);
+        update(&mut parameters);
+        self.set_parameters(symbol, parameters);
+    }
+
+    // Applies to every symbol without its own override
+    pub fn set_default_parameters(&mut self, parameters: VolatilityParameters) {
+        for (symbol, model) in self.models.iter_mut() {
+            if !self.symbol_parameters.contains_key(symbol) {
+                model.apply_parameters(&parameters);
+            }
+        }
+        self.default_parameters = parameters;
+    }
+
+    // Runs every model's due refits, returning the symbols that were refit
+    pub fn run_scheduled_refits(&mut self) -> Vec<String> {
+        self.models.iter_mut()
+            .filter_map(|(symbol, model)| model.run_scheduled_refits().then(|| symbol.clone()))
+            .collect()
+    }
+
+    // Latest annualized realized volatility of every tracked symbol
+    pub fn volatilities(&self) -> HashMap<String, f64> {
+        self.models.iter()
+            .map(|(symbol, model)| (symbol.clone(), model.get_volatility()))
+            .collect()
+    }
+}
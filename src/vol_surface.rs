@@ -0,0 +1,375 @@
+use crate::*;
+
+const MILLIS_PER_YEAR: f64 = 365.0 * 86_400_000.0;
+
+// Log-moneyness grid the arbitrage checks are evaluated on
+const ARBITRAGE_GRID: (f64, f64, usize) = (-1.5, 1.5, 61);
+
+// Implied volatility surface for one underlying: raw SVI per expiry slice,
+// linear interpolation of total variance across expiries at fixed log-moneyness
+#[derive(Debug, Clone)]
+pub struct VolatilitySurface {
+    underlying: String,
+    points: Vec<SurfacePoint>,
+    slices: Vec<SviSlice>, // Sorted by expiry
+    as_of: f64,            // Latest quote time
+    fitted_as_of: f64,     // Valuation time the slices were fit at
+    min_points_per_slice: usize,
+}
+
+#[derive(Debug, Clone)]
+struct SurfacePoint {
+    expiry: f64,
+    strike: f64,
+    forward: f64, // As of the quote's own timestamp
+    implied_volatility: f64,
+}
+
+// Raw SVI: w(k) = a + b * (rho * (k - m) + sqrt((k - m)^2 + sigma^2))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SviParameters {
+    pub a: f64,
+    pub b: f64,
+    pub rho: f64,
+    pub m: f64,
+    pub sigma: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SviSlice {
+    pub expiry: f64,
+    pub time_to_expiry: f64,
+    pub forward: f64, // Mean of the slice quotes' forwards
+    pub params: SviParameters,
+    pub rmse: f64, // In implied volatility
+    pub points: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ArbitrageKind {
+    Butterfly,
+    Calendar,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArbitrageViolation {
+    pub kind: ArbitrageKind,
+    pub expiry: f64,
+    pub log_moneyness: f64,
+    pub value: f64, // g(k) for butterfly, total variance decrease for calendar
+}
+
+impl SviParameters {
+    pub fn total_variance(&self, k: f64) -> f64 {
+        let x = k - self.m;
+        self.a + self.b * (self.rho * x + (x * x + self.sigma * self.sigma).sqrt())
+    }
+
+    fn first_derivative(&self, k: f64) -> f64 {
+        let x = k - self.m;
+        self.b * (self.rho + x / (x * x + self.sigma * self.sigma).sqrt())
+    }
+
+    fn second_derivative(&self, k: f64) -> f64 {
+        let x = k - self.m;
+        let s2 = self.sigma * self.sigma;
+        self.b * s2 / (x * x + s2).powf(1.5)
+    }
+
+    // Gatheral's density condition; negative values mean butterfly arbitrage
+    pub fn butterfly_density(&self, k: f64) -> f64 {
+        let w = self.total_variance(k);
+        let w1 = self.first_derivative(k);
+        let w2 = self.second_derivative(k);
+        (1.0 - k * w1 / (2.0 * w)).powi(2) - w1 * w1 / 4.0 * (1.0 / w + 0.25) + w2 / 2.0
+    }
+
+    // Optimizer coordinates -> b >= 0, |rho| < 1, sigma > 0
+    fn from_unconstrained(x: &[f64]) -> Self {
+        Self {
+            a: x[0],
+            b: x[1].exp(),
+            rho: x[2].tanh(),
+            m: x[3],
+            sigma: x[4].exp(),
+        }
+    }
+
+    fn min_total_variance(&self) -> f64 {
+        self.a + self.b * self.sigma * (1.0 - self.rho * self.rho).sqrt()
+    }
+}
+
+impl VolatilitySurface {
+    pub fn new(underlying: &str) -> Self {
+        Self {
+            underlying: underlying.to_string(),
+            points: Vec::new(),
+            slices: Vec::new(),
+            as_of: 0.0,
+            fitted_as_of: 0.0,
+            min_points_per_slice: 5,
+        }
+    }
+
+    // Replaces any earlier point at the same expiry and strike
+    pub fn add_quote(&mut self, quote: &OptionQuote, implied_volatility: f64) {
+        let forward = match quote.model {
+            PricingModel::BlackScholes => quote.underlying_price *
+                ((quote.rate - quote.dividend_yield) * quote.time_to_expiry()).exp(),
+            PricingModel::Black76 => quote.underlying_price,
+        };
+        let point = SurfacePoint {
+            expiry: quote.expiry,
+            strike: quote.strike,
+            forward,
+            implied_volatility,
+        };
+
+        self.as_of = self.as_of.max(quote.timestamp);
+        self.points.retain(|p| !(p.expiry == quote.expiry && p.strike == quote.strike));
+        self.points.push(point);
+    }
+
+    // Drops expired points and refits every slice with enough strikes
+    pub fn fit(&mut self) -> Vec<SviSlice> {
+        let as_of = self.as_of;
+        self.points.retain(|p| p.expiry > as_of);
+        self.fitted_as_of = as_of;
+
+        let mut expiries: Vec<f64> = self.points.iter().map(|p| p.expiry).collect();
+        expiries.sort_by(|a, b| a.total_cmp(b));
+        expiries.dedup();
+
+        self.slices = expiries.into_iter()
+            .filter_map(|expiry| self.fit_slice(expiry))
+            .collect();
+        console_log!("Fitted {} SVI slices for {}", self.slices.len(), self.underlying);
+        self.slices.clone()
+    }
+
+    fn fit_slice(&self, expiry: f64) -> Option<SviSlice> {
+        let points: Vec<&SurfacePoint> = self.points.iter().filter(|p| p.expiry == expiry).collect();
+        if points.len() < self.min_points_per_slice {
+            return None;
+        }
+
+        // Quoted vols are converted to total variance on the fit clock, so the
+        // fit, its error and later queries all share one time base
+        let time_to_expiry = (expiry - self.fitted_as_of) / MILLIS_PER_YEAR;
+        let forward = points.iter().map(|p| p.forward).sum::<f64>() / points.len() as f64;
+        let market: Vec<(f64, f64)> = points.iter()
+            .map(|p| ((p.strike / p.forward).ln(), p.implied_volatility * p.implied_volatility * time_to_expiry))
+            .collect();
+
+        // Start from a flat smile at the average variance
+        let mean_variance = market.iter().map(|(_, w)| w).sum::<f64>() / market.len() as f64;
+        let start = [mean_variance * 0.5, (mean_variance * 0.5).max(1e-6).ln(), 0.0, 0.0, 0.1f64.ln()];
+
+        let objective = |x: &[f64]| {
+            let params = SviParameters::from_unconstrained(x);
+            let error: f64 = market.iter()
+                .map(|&(k, w)| (params.total_variance(k) - w).powi(2))
+                .sum();
+            // Total variance must stay non-negative everywhere
+            let penalty = (-params.min_total_variance()).max(0.0) * 1e3;
+            error + penalty
+        };
+        let result = nelder_mead(&objective, &start, 0.5, 2000, 1e-12);
+        let params = SviParameters::from_unconstrained(&result.point);
+
+        let squared_error: f64 = market.iter()
+            .map(|&(k, w)| {
+                let model_vol = (params.total_variance(k).max(0.0) / time_to_expiry).sqrt();
+                (model_vol - (w / time_to_expiry).sqrt()).powi(2)
+            })
+            .sum();
+
+        Some(SviSlice {
+            expiry,
+            time_to_expiry,
+            forward,
+            params,
+            rmse: (squared_error / market.len() as f64).sqrt(),
+            points: market.len(),
+        })
+    }
+
+    // Total variance at log-moneyness k and time t (years), linear in t between
+    // slices, proportional to t before the first and at constant vol after the last
+    fn total_variance(&self, k: f64, t: f64) -> Option<f64> {
+        let first = self.slices.first()?;
+        let last = self.slices.last()?;
+
+        if t <= first.time_to_expiry {
+            return Some(first.params.total_variance(k) * t / first.time_to_expiry);
+        }
+        if t >= last.time_to_expiry {
+            return Some(last.params.total_variance(k) * t / last.time_to_expiry);
+        }
+
+        self.slices.windows(2)
+            .find(|pair| t <= pair[1].time_to_expiry)
+            .map(|pair| {
+                let (near, far) = (&pair[0], &pair[1]);
+                let weight = (t - near.time_to_expiry) / (far.time_to_expiry - near.time_to_expiry);
+                (1.0 - weight) * near.params.total_variance(k) + weight * far.params.total_variance(k)
+            })
+    }
+
+    // Forward interpolated log-linearly in time
+    fn forward(&self, t: f64) -> Option<f64> {
+        let first = self.slices.first()?;
+        let last = self.slices.last()?;
+        if t <= first.time_to_expiry || self.slices.len() == 1 {
+            return Some(first.forward);
+        }
+        if t >= last.time_to_expiry {
+            return Some(last.forward);
+        }
+
+        self.slices.windows(2)
+            .find(|pair| t <= pair[1].time_to_expiry)
+            .map(|pair| {
+                let (near, far) = (&pair[0], &pair[1]);
+                let weight = (t - near.time_to_expiry) / (far.time_to_expiry - near.time_to_expiry);
+                ((1.0 - weight) * near.forward.ln() + weight * far.forward.ln()).exp()
+            })
+    }
+
+    // Implied volatility for any strike and expiry (epoch milliseconds), on the
+    // same clock the slices were fit against
+    pub fn implied_volatility(&self, strike: f64, expiry: f64) -> Option<f64> {
+        let t = (expiry - self.fitted_as_of) / MILLIS_PER_YEAR;
+        if t <= 0.0 || strike <= 0.0 {
+            return None;
+        }
+
+        let k = (strike / self.forward(t)?).ln();
+        let variance = self.total_variance(k, t)?;
+        Some((variance.max(0.0) / t).sqrt())
+    }
+
+    pub fn check_arbitrage(&self) -> Vec<ArbitrageViolation> {
+        let (start, end, steps) = ARBITRAGE_GRID;
+        let grid: Vec<f64> = (0..steps)
+            .map(|i| start + (end - start) * i as f64 / (steps - 1) as f64)
+            .collect();
+        let mut violations = Vec::new();
+
+        for slice in &self.slices {
+            for &k in &grid {
+                let density = slice.params.butterfly_density(k);
+                if density < 0.0 {
+                    violations.push(ArbitrageViolation {
+                        kind: ArbitrageKind::Butterfly,
+                        expiry: slice.expiry,
+                        log_moneyness: k,
+                        value: density,
+                    });
+                }
+            }
+        }
+
+        // Total variance must not decrease with expiry at any moneyness
+        for pair in self.slices.windows(2) {
+            for &k in &grid {
+                let decrease = pair[0].params.total_variance(k) - pair[1].params.total_variance(k);
+                if decrease > 0.0 {
+                    violations.push(ArbitrageViolation {
+                        kind: ArbitrageKind::Calendar,
+                        expiry: pair[1].expiry,
+                        log_moneyness: k,
+                        value: decrease,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    pub fn get_slices(&self) -> &[SviSlice] {
+        &self.slices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MS: f64 = 86_400_000.0;
+
+    // Black-76 quote on a forward of 100; the surface only reads the strike,
+    // expiry, forward and timestamp
+    fn quote(strike: f64, expiry: f64, timestamp: f64) -> OptionQuote {
+        OptionQuote {
+            symbol: format!("OPT-{}-{}", strike, expiry),
+            underlying: "UND".to_string(),
+            strike,
+            expiry,
+            option_type: OptionType::Call,
+            bid: 0.0,
+            ask: 0.0,
+            underlying_price: 100.0,
+            timestamp,
+            model: PricingModel::Black76,
+            rate: 0.0,
+            dividend_yield: 0.0,
+        }
+    }
+
+    fn add_smile(surface: &mut VolatilitySurface, expiry: f64, timestamp: f64, volatility: impl Fn(f64) -> f64) {
+        for i in 0..13 {
+            let k = -0.6 + 0.1 * i as f64;
+            surface.add_quote(&quote(100.0 * k.exp(), expiry, timestamp), volatility(k));
+        }
+    }
+
+    #[test]
+    fn svi_fit_recovers_a_known_smile() {
+        let params = SviParameters { a: 0.02, b: 0.1, rho: -0.4, m: 0.05, sigma: 0.2 };
+        let t = 0.5;
+        let expiry = t * MILLIS_PER_YEAR;
+        let mut surface = VolatilitySurface::new("UND");
+        add_smile(&mut surface, expiry, 0.0, |k| (params.total_variance(k) / t).sqrt());
+
+        let slices = surface.fit();
+        assert_eq!(slices.len(), 1);
+        assert!(slices[0].rmse < 1e-4, "rmse {}", slices[0].rmse);
+        for k in [-0.5, -0.2, 0.0, 0.3, 0.5] {
+            let fitted = surface.implied_volatility(100.0 * f64::exp(k), expiry).unwrap();
+            let expected = (params.total_variance(k) / t).sqrt();
+            assert!((fitted - expected).abs() < 1e-3, "k={}: {} vs {}", k, fitted, expected);
+        }
+        assert!(surface.check_arbitrage().is_empty());
+    }
+
+    #[test]
+    fn stale_quotes_keep_their_quoted_volatility() {
+        let expiry = 60.0 * DAY_MS;
+        let mut surface = VolatilitySurface::new("UND");
+        add_smile(&mut surface, expiry, 0.0, |_| 0.25);
+        // A single fresher quote moves the surface clock forward 30 days
+        surface.add_quote(&quote(100.0, expiry, 30.0 * DAY_MS), 0.25);
+
+        surface.fit();
+        for strike in [70.0, 100.0, 140.0] {
+            let volatility = surface.implied_volatility(strike, expiry).unwrap();
+            assert!((volatility - 0.25).abs() < 1e-3, "strike {}: {}", strike, volatility);
+        }
+    }
+
+    #[test]
+    fn calendar_arbitrage_fires_on_crossed_slices() {
+        let mut surface = VolatilitySurface::new("UND");
+        // The near slice carries more total variance than the far one
+        add_smile(&mut surface, 0.25 * MILLIS_PER_YEAR, 0.0, |_| 0.5);
+        add_smile(&mut surface, 0.5 * MILLIS_PER_YEAR, 0.0, |_| 0.2);
+
+        surface.fit();
+        let violations = surface.check_arbitrage();
+        assert!(violations.iter().any(|v| v.kind == ArbitrageKind::Calendar));
+        assert!(violations.iter().all(|v| v.kind != ArbitrageKind::Butterfly));
+    }
+}
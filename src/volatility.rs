@@ -1,22 +1,47 @@
+use crate::*;
 use std::collections::VecDeque;
 
+// Annualization basis: a 6h15m cash session (09:15-15:30 IST) and 252 sessions a year
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+pub const TRADING_SECONDS_PER_DAY: f64 = 22_500.0;
+
+// Shortest and longest elapsed time a single return is allowed to span
+const MIN_RETURN_INTERVAL_SECS: f64 = 0.001;
+const MAX_RETURN_INTERVAL_SECS: f64 = TRADING_SECONDS_PER_DAY;
+
+// Tick returns needed before the noise-robust estimators are meaningful
+const MIN_NOISE_ROBUST_RETURNS: usize = 50;
+
 #[derive(Debug, Clone)]
 pub struct VolatilityModel {
     prices: VecDeque<f64>,
     returns: VecDeque<f64>,
-    garch_params: GarchParameters,
+    return_intervals: VecDeque<f64>, // Seconds spanned by each return
+    continuous_returns: VecDeque<f64>, // Returns with detected jumps truncated
+    jump_detector: JumpDetector,
+    last_timestamp: Option<f64>,
+    garch_params: ConditionalVarianceModel,
+    garch_state: GarchState,
+    regime_model: MarkovSwitchingModel,
+    regime_returns_since_fit: usize,
+    regime_fit_pending: bool, // First fit is tried as soon as the window allows
     ewma_params: EwmaParameters,
+    bar_builder: BarBuilder,
+    estimator_weights: Vec<EstimatorWeight>,
     realized_volatility: f64,
     implied_volatility: f64,
     volatility_regime: VolatilityRegime,
 }
 
+// Recursive GARCH conditional variance and the refit schedule
 #[derive(Debug, Clone)]
-struct GarchParameters {
-    omega: f64,    // Constant term
-    alpha: f64,    // ARCH coefficient
-    beta: f64,     // GARCH coefficient
-    lambda: f64,   // Long-run variance
+struct GarchState {
+    conditional_variance: f64, // One-step-ahead variance per second for the next return
+    last_fit: Option<GarchFit>,
+    refit_interval: usize,     // Returns between scheduled refits
+    returns_since_fit: usize,
+    fit_window: usize,
+    min_fit_observations: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +50,83 @@ struct EwmaParameters {
     window_size: usize,
 }
 
+// Time-bucketed OHLC bars built from the tick stream
+#[derive(Debug, Clone)]
+struct BarBuilder {
+    interval_secs: f64,
+    current_bar: Option<OhlcBar>,
+    bars: VecDeque<OhlcBar>,
+    max_bars: usize,
+    range_window: usize, // Completed bars used by the range estimators
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OhlcBar {
+    pub start_time: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VolatilityEstimator {
+    Ewma,
+    Garch,
+    Parkinson,
+    GarmanKlass,
+    RogersSatchell,
+    YangZhang,
+    TwoScale,
+    RealizedKernel,
+    PreAveraging,
+}
+
+// Share of an estimator in the blended realized volatility
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimatorWeight {
+    pub estimator: VolatilityEstimator,
+    pub weight: f64,
+}
+
+// Lee-Mykland jump test settings and the jumps it has flagged
+#[derive(Debug, Clone)]
+struct JumpDetector {
+    local_window: usize,   // Returns used for the local bipower volatility
+    significance: f64,     // Test size; 1% is the Lee-Mykland default
+    pending_event: Option<JumpEvent>,
+    recent_jumps: VecDeque<JumpEvent>,
+    max_recent_jumps: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpEvent {
+    pub timestamp: f64,
+    pub price: f64,
+    pub return_value: f64,
+    pub test_statistic: f64, // |L| standardized by the Gumbel location and scale
+}
+
+// Split of realized variance over the tick window into its continuous and jump parts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JumpStatistics {
+    pub realized_variance: f64,
+    pub bipower_variation: f64,
+    pub jump_variation: f64,
+    pub jump_share: f64,
+    pub continuous_volatility: f64, // Annualized, from bipower variation
+    pub recent_jumps: Vec<JumpEvent>,
+}
+
+// Same volatility expressed over the horizons quoting and risk care about
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VolatilityEstimate {
+    pub per_second: f64,
+    pub per_day: f64,
+    pub annualized: f64,
+    pub noise_variance: f64, // Variance of the microstructure noise in log price
+}
+
 #[derive(Debug, Clone)]
 enum VolatilityRegime {
     Low,
@@ -33,29 +135,76 @@ enum VolatilityRegime {
     Extreme,
 }
 
+impl Default for VolatilityModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VolatilityModel {
     pub fn new() -> Self {
         Self {
             prices: VecDeque::new(),
             returns: VecDeque::new(),
-            garch_params: GarchParameters {
+            return_intervals: VecDeque::new(),
+            continuous_returns: VecDeque::new(),
+            jump_detector: JumpDetector {
+                local_window: 100,
+                significance: 0.01,
+                pending_event: None,
+                recent_jumps: VecDeque::new(),
+                max_recent_jumps: 100,
+            },
+            last_timestamp: None,
+            garch_params: ConditionalVarianceModel::Garch(GarchParameters {
                 omega: 0.000001,
                 alpha: 0.1,
                 beta: 0.85,
-                lambda: 0.000016,
+            }),
+            garch_state: GarchState {
+                conditional_variance: 0.0,
+                last_fit: None,
+                refit_interval: 250,
+                returns_since_fit: 0,
+                fit_window: 500,
+                min_fit_observations: 100,
             },
+            regime_model: MarkovSwitchingModel::new(2),
+            regime_returns_since_fit: 0,
+            regime_fit_pending: true,
             ewma_params: EwmaParameters {
                 decay_factor: 0.94,
                 window_size: 100,
             },
+            bar_builder: BarBuilder {
+                interval_secs: 60.0,
+                current_bar: None,
+                bars: VecDeque::new(),
+                max_bars: 500,
+                range_window: 30,
+            },
+            estimator_weights: vec![
+                EstimatorWeight { estimator: VolatilityEstimator::Ewma, weight: 0.6 },
+                EstimatorWeight { estimator: VolatilityEstimator::Garch, weight: 0.4 },
+            ],
             realized_volatility: 0.0,
             implied_volatility: 0.0,
             volatility_regime: VolatilityRegime::Normal,
         }
     }
 
-    pub fn update(&mut self, price: f64, _timestamp: f64) -> f64 {
+    // `timestamp` is in milliseconds; ticks may arrive at any spacing. Ticks
+    // at or before the last recorded timestamp carry no elapsed time, so they
+    // are not recorded and their move is carried into the next return.
+    pub fn update(&mut self, price: f64, timestamp: f64) -> f64 {
+        self.bar_builder.update(price, timestamp);
+        let elapsed_secs = match self.last_timestamp {
+            Some(last) if timestamp <= last => return self.realized_volatility,
+            Some(last) => ((timestamp - last) / 1000.0).clamp(MIN_RETURN_INTERVAL_SECS, MAX_RETURN_INTERVAL_SECS),
+            None => 0.0, // First tick; no return yet
+        };
         self.prices.push_back(price);
+        self.last_timestamp = Some(timestamp);
 
         // Maintain window size
         if self.prices.len() > 1000 {
@@ -67,11 +216,27 @@ impl VolatilityModel {
             let prev_price = self.prices[self.prices.len() - 2];
             let return_value = (price / prev_price).ln();
 
+            // Jumps are kept in the raw returns but truncated to the local
+            // continuous volatility for the diffusive estimators
+            let continuous_return = match self.test_for_jump(return_value, elapsed_secs, price, timestamp) {
+                Some(local_volatility) => return_value.signum() * local_volatility * elapsed_secs.sqrt(),
+                None => return_value,
+            };
+
             self.returns.push_back(return_value);
+            self.return_intervals.push_back(elapsed_secs);
+            self.continuous_returns.push_back(continuous_return);
 
             if self.returns.len() > 500 {
                 self.returns.pop_front();
+                self.return_intervals.pop_front();
+                self.continuous_returns.pop_front();
             }
+
+            // GARCH and the regime model run on returns scaled to a one-second interval
+            let normalized_return = continuous_return / elapsed_secs.sqrt();
+            self.update_garch_variance(normalized_return);
+            self.update_regime_model(normalized_return);
         }
 
         // Update volatility estimates
@@ -88,17 +253,198 @@ impl VolatilityModel {
             return 0.0;
         }
 
-        // Calculate EWMA volatility
-        let ewma_vol = self.calculate_ewma_volatility();
+        // Weighted average of the configured estimators, all per second.
+        // Estimators without enough data yet drop out of the blend.
+        let (weighted_vol, total_weight) = self.estimator_weights.iter()
+            .filter_map(|w| self.estimator_per_second_volatility(w.estimator).map(|vol| (vol, w.weight)))
+            .fold((0.0, 0.0), |(sum, total), (vol, weight)| (sum + vol * weight, total + weight));
+
+        if total_weight > 0.0 {
+            annualize(weighted_vol / total_weight)
+        } else {
+            annualize(self.calculate_ewma_volatility())
+        }
+    }
+
+    fn estimator_per_second_volatility(&self, estimator: VolatilityEstimator) -> Option<f64> {
+        match estimator {
+            VolatilityEstimator::Ewma => Some(self.calculate_ewma_volatility()),
+            VolatilityEstimator::Garch => Some(self.calculate_garch_volatility()),
+            VolatilityEstimator::TwoScale |
+            VolatilityEstimator::RealizedKernel |
+            VolatilityEstimator::PreAveraging => self.noise_robust_volatility(estimator),
+            range_estimator => self.bar_builder.range_volatility(range_estimator),
+        }
+    }
+
+    // Annualized volatility from a single estimator, if it has enough data
+    pub fn get_estimator_volatility(&self, estimator: VolatilityEstimator) -> Option<f64> {
+        self.estimator_per_second_volatility(estimator).map(annualize)
+    }
 
-        // Calculate GARCH volatility
-        let garch_vol = self.calculate_garch_volatility();
+    // A single estimator with weight 1.0 selects it outright
+    pub fn set_estimator_weights(&mut self, weights: Vec<EstimatorWeight>) {
+        self.estimator_weights = weights.into_iter().filter(|w| w.weight > 0.0).collect();
+    }
 
-        // Weighted average of different models
-        let combined_vol = 0.6 * ewma_vol + 0.4 * garch_vol;
+    // Changing the interval discards bars built on the old one
+    pub fn set_bar_interval(&mut self, interval_secs: f64, range_window: usize) {
+        let builder = &mut self.bar_builder;
+        builder.interval_secs = interval_secs.max(1.0);
+        builder.range_window = range_window.max(2);
+        builder.current_bar = None;
+        builder.bars.clear();
+    }
+
+    // Only settings that differ from the current ones are applied, so
+    // reapplying the same parameters keeps bars and fitted models
+    pub fn apply_parameters(&mut self, parameters: &VolatilityParameters) {
+        self.set_variance_model(parameters.variance_model);
+        self.set_garch_refit_schedule(parameters.refit_interval, parameters.fit_window);
+        self.set_estimator_weights(parameters.estimator_weights.clone());
+        if parameters.bar_interval_secs.max(1.0) != self.bar_builder.interval_secs ||
+           parameters.range_window.max(2) != self.bar_builder.range_window {
+            self.set_bar_interval(parameters.bar_interval_secs, parameters.range_window);
+        }
+        if parameters.regime_states.clamp(2, 4) != self.regime_model.num_states() {
+            self.set_regime_states(parameters.regime_states);
+        }
+    }
 
-        // Annualize the volatility (assuming daily data)
-        combined_vol * (252.0_f64).sqrt()
+    pub fn get_bars(&self) -> Vec<OhlcBar> {
+        self.bar_builder.bars.iter().cloned().collect()
+    }
+
+    pub fn get_volatility_estimate(&self) -> VolatilityEstimate {
+        let per_second = self.realized_volatility / (TRADING_DAYS_PER_YEAR * TRADING_SECONDS_PER_DAY).sqrt();
+        VolatilityEstimate {
+            per_second,
+            per_day: per_second * TRADING_SECONDS_PER_DAY.sqrt(),
+            annualized: self.realized_volatility,
+            noise_variance: self.calculate_noise_variance(),
+        }
+    }
+
+    // Bid-ask bounce adds 2 * noise variance to every squared tick return, so
+    // whatever realized variance exceeds the TSRV estimate is attributed to noise
+    fn calculate_noise_variance(&self) -> f64 {
+        if self.returns.is_empty() {
+            return 0.0;
+        }
+        let returns: Vec<f64> = self.returns.iter().cloned().collect();
+        let realized_variance: f64 = returns.iter().map(|r| r * r).sum();
+        let integrated_variance = if returns.len() >= MIN_NOISE_ROBUST_RETURNS {
+            two_scale_realized_variance(&returns).clamp(0.0, realized_variance)
+        } else {
+            0.0
+        };
+        (realized_variance - integrated_variance) / (2.0 * returns.len() as f64)
+    }
+
+    // Lee-Mykland test of the new return against the bipower volatility of the
+    // preceding window. Returns the local per-second volatility when it is a jump.
+    fn test_for_jump(&mut self, return_value: f64, elapsed_secs: f64, price: f64, timestamp: f64) -> Option<f64> {
+        let window = self.jump_detector.local_window;
+        if self.returns.len() < window {
+            return None;
+        }
+
+        let normalized = self.normalized_returns();
+        let local = &normalized[normalized.len() - window..];
+        let local_variance = local.windows(2).map(|w| w[0].abs() * w[1].abs()).sum::<f64>()
+            * std::f64::consts::FRAC_PI_2 / (window - 1) as f64;
+        if local_variance <= 0.0 {
+            return None;
+        }
+        let local_volatility = local_variance.sqrt();
+
+        // Maximum of n standard normals is Gumbel with these location and scale,
+        // where n counts the buffered returns tested against a full window
+        // (the Gumbel limit needs at least two)
+        let n = (self.returns.len() + 1 - window).max(2) as f64;
+        let c = (2.0 / std::f64::consts::PI).sqrt();
+        let log_n = n.ln();
+        let location = (2.0 * log_n).sqrt() / c -
+            (std::f64::consts::PI.ln() + log_n.ln()) / (2.0 * c * (2.0 * log_n).sqrt());
+        let scale = 1.0 / (c * (2.0 * log_n).sqrt());
+        let critical_value = -(-(1.0 - self.jump_detector.significance).ln()).ln();
+
+        // The constants assume Lee-Mykland's uncorrected bipower scale c * sigma,
+        // while local_volatility already carries the pi / 2 correction
+        let statistic = (return_value / elapsed_secs.sqrt()).abs() / local_volatility / c;
+        let test_statistic = (statistic - location) / scale;
+        if test_statistic <= critical_value {
+            return None;
+        }
+
+        let event = JumpEvent { timestamp, price, return_value, test_statistic };
+        let detector = &mut self.jump_detector;
+        detector.recent_jumps.push_back(event.clone());
+        if detector.recent_jumps.len() > detector.max_recent_jumps {
+            detector.recent_jumps.pop_front();
+        }
+        detector.pending_event = Some(event);
+        Some(local_volatility)
+    }
+
+    // The jump flagged by the latest update, if not already taken
+    pub fn take_jump_event(&mut self) -> Option<JumpEvent> {
+        self.jump_detector.pending_event.take()
+    }
+
+    pub fn get_jump_statistics(&self) -> JumpStatistics {
+        let realized_variance: f64 = self.returns.iter().map(|r| r * r).sum();
+        let bipower_variation = self.returns.iter().zip(self.returns.iter().skip(1))
+            .map(|(a, b)| a.abs() * b.abs())
+            .sum::<f64>() * std::f64::consts::FRAC_PI_2;
+        let jump_variation = (realized_variance - bipower_variation).max(0.0);
+        let elapsed_secs: f64 = self.return_intervals.iter().sum();
+
+        JumpStatistics {
+            realized_variance,
+            bipower_variation,
+            jump_variation,
+            jump_share: if realized_variance > 0.0 { jump_variation / realized_variance } else { 0.0 },
+            continuous_volatility: if elapsed_secs > 0.0 {
+                annualize((bipower_variation / elapsed_secs).sqrt())
+            } else {
+                0.0
+            },
+            recent_jumps: self.jump_detector.recent_jumps.iter().cloned().collect(),
+        }
+    }
+
+    // Per-second volatility from a noise-robust integrated variance estimate
+    // over the tick window
+    fn noise_robust_volatility(&self, estimator: VolatilityEstimator) -> Option<f64> {
+        if self.returns.len() < MIN_NOISE_ROBUST_RETURNS {
+            return None;
+        }
+        let returns: Vec<f64> = self.returns.iter().cloned().collect();
+        let integrated_variance = match estimator {
+            VolatilityEstimator::TwoScale => two_scale_realized_variance(&returns),
+            VolatilityEstimator::RealizedKernel => realized_kernel(&returns),
+            VolatilityEstimator::PreAveraging => pre_averaged_variance(&returns),
+            _ => return None,
+        };
+        let elapsed_secs: f64 = self.return_intervals.iter().sum();
+
+        Some((integrated_variance.max(0.0) / elapsed_secs).sqrt())
+    }
+
+    // Mean seconds between the observations in the return window
+    fn average_return_interval(&self) -> f64 {
+        if self.return_intervals.is_empty() {
+            return 1.0;
+        }
+        self.return_intervals.iter().sum::<f64>() / self.return_intervals.len() as f64
+    }
+
+    // Jump-truncated returns scaled to one second
+    fn normalized_returns(&self) -> Vec<f64> {
+        self.continuous_returns.iter().zip(self.return_intervals.iter())
+            .map(|(r, dt)| r / dt.sqrt())
+            .collect()
     }
 
     fn calculate_ewma_volatility(&self) -> f64 {
@@ -106,14 +452,16 @@ impl VolatilityModel {
             return 0.0;
         }
 
+        // Weighted squared returns over weighted elapsed time gives a
+        // per-second variance that is unbiased for irregular spacing
         let mut weighted_var = 0.0;
         let mut weight_sum = 0.0;
         let decay = self.ewma_params.decay_factor;
 
-        for (i, &return_val) in self.returns.iter().rev().enumerate() {
+        for (i, (&return_val, &interval)) in self.continuous_returns.iter().zip(self.return_intervals.iter()).rev().enumerate() {
             let weight = decay.powi(i as i32);
             weighted_var += weight * return_val.powi(2);
-            weight_sum += weight;
+            weight_sum += weight * interval;
 
             if i >= self.ewma_params.window_size {
                 break;
@@ -132,25 +480,122 @@ impl VolatilityModel {
             return 0.0;
         }
 
-        // Simplified GARCH(1,1) calculation
-        let recent_returns: Vec<f64> = self.returns.iter().rev().take(10).cloned().collect();
+        self.garch_state.conditional_variance.sqrt()
+    }
+
+    // Carries the conditional variance forward one return. Refits are left to
+    // run_scheduled_refits so the optimizer never runs on the tick path.
+    fn update_garch_variance(&mut self, return_value: f64) {
+        let state = &mut self.garch_state;
+        state.conditional_variance = if state.conditional_variance > 0.0 {
+            self.garch_params.next_variance(return_value, state.conditional_variance)
+        } else {
+            return_value * return_value
+        };
+
+        state.returns_since_fit += 1;
+    }
+
+    fn update_regime_model(&mut self, normalized_return: f64) {
+        self.regime_model.filter(normalized_return);
+        self.regime_returns_since_fit += 1;
+    }
+
+    // Runs the GARCH and regime refits that have come due. The regime model follows
+    // the GARCH schedule, and a failed EM fit waits out a full refit interval
+    // before it is tried again. Returns whether either model was refit.
+    pub fn run_scheduled_refits(&mut self) -> bool {
+        let schedule = &self.garch_state;
+        if self.returns.len() < schedule.min_fit_observations {
+            return false;
+        }
+        let garch_due = schedule.returns_since_fit >= schedule.refit_interval;
+        let regime_due = self.regime_fit_pending || self.regime_returns_since_fit >= schedule.refit_interval;
+
+        let mut refitted = false;
+        if garch_due {
+            refitted |= self.refit_garch().is_some();
+        }
+        if regime_due {
+            refitted |= self.refit_regime_model().is_some();
+        }
+        refitted
+    }
+
+    pub fn refit_regime_model(&mut self) -> Option<RegimeState> {
+        self.regime_returns_since_fit = 0;
+        self.regime_fit_pending = false;
+        let normalized = self.normalized_returns();
+        let window = &normalized[normalized.len().saturating_sub(self.garch_state.fit_window)..];
+        if !self.regime_model.fit(window) {
+            return None;
+        }
+        Some(self.get_regime_state())
+    }
+
+    // Replaces the regime model with an unfitted one with `num_states` regimes
+    pub fn set_regime_states(&mut self, num_states: usize) {
+        self.regime_model = MarkovSwitchingModel::new(num_states);
+        self.regime_returns_since_fit = 0;
+        self.regime_fit_pending = true;
+    }
+
+    pub fn get_regime_state(&self) -> RegimeState {
+        if !self.regime_model.is_fitted() {
+            return RegimeState::default();
+        }
+        self.regime_model.state(self.average_return_interval())
+    }
+
+    // Re-estimates the conditional variance model by MLE on the latest window and rebuilds the
+    // conditional variance under the new parameters
+    pub fn refit_garch(&mut self) -> Option<GarchFit> {
+        self.garch_state.returns_since_fit = 0;
+        let normalized = self.normalized_returns();
+        let window = &normalized[normalized.len().saturating_sub(self.garch_state.fit_window)..];
+
+        let fit = fit_variance_model(window, &self.garch_params)?;
+        self.garch_params = fit.params.clone();
+        let sample_variance = window.iter().map(|r| r * r).sum::<f64>() / window.len() as f64;
+        self.garch_state.conditional_variance = window.iter()
+            .fold(sample_variance, |variance, &r| self.garch_params.next_variance(r, variance));
+        self.garch_state.last_fit = Some(fit.clone());
+        Some(fit)
+    }
 
-        // Calculate sample variance for initialization
-        let mean_return = recent_returns.iter().sum::<f64>() / recent_returns.len() as f64;
-        let sample_variance = recent_returns.iter()
-            .map(|r| (r - mean_return).powi(2))
-            .sum::<f64>() / (recent_returns.len() - 1) as f64;
+    pub fn set_garch_refit_schedule(&mut self, refit_interval: usize, fit_window: usize) {
+        self.garch_state.refit_interval = refit_interval.max(1);
+        self.garch_state.fit_window = fit_window.max(self.garch_state.min_fit_observations);
+    }
 
-        let mut conditional_variance = sample_variance;
+    pub fn get_garch_fit(&self) -> Option<&GarchFit> {
+        self.garch_state.last_fit.as_ref()
+    }
 
-        // Update conditional variance using GARCH(1,1)
-        for &return_val in recent_returns.iter().rev() {
-            conditional_variance = self.garch_params.omega +
-                                 self.garch_params.alpha * return_val.powi(2) +
-                                 self.garch_params.beta * conditional_variance;
+    // Switches the conditional variance model, refitting immediately when
+    // enough history is available
+    pub fn set_variance_model(&mut self, variant: GarchVariant) {
+        if self.garch_params.variant() == variant {
+            return;
+        }
+        self.garch_params = ConditionalVarianceModel::default_for(variant, self.garch_state.conditional_variance);
+        self.garch_state.last_fit = None;
+        if self.continuous_returns.len() >= self.garch_state.min_fit_observations {
+            self.refit_garch();
         }
+    }
+
+    pub fn get_variance_model(&self) -> GarchVariant {
+        self.garch_params.variant()
+    }
 
-        conditional_variance.sqrt()
+    pub fn compare_variance_models(&self) -> Vec<VarianceModelComparison> {
+        let normalized = self.normalized_returns();
+        let window = &normalized[normalized.len().saturating_sub(self.garch_state.fit_window)..];
+        if window.len() < self.garch_state.min_fit_observations {
+            return Vec::new();
+        }
+        compare_variance_models(window)
     }
 
     fn update_volatility_regime(&mut self) {
@@ -171,6 +616,15 @@ impl VolatilityModel {
         self.realized_volatility
     }
 
+    // Latest implied volatility solved from option market data
+    pub fn set_implied_volatility(&mut self, implied_volatility: f64) {
+        self.implied_volatility = implied_volatility;
+    }
+
+    pub fn get_implied_volatility(&self) -> f64 {
+        self.implied_volatility
+    }
+
     pub fn get_volatility_percentile(&self) -> f64 {
         if self.returns.len() < 50 {
             return 0.5; // Default to median
@@ -179,15 +633,10 @@ impl VolatilityModel {
         // Calculate historical volatilities
         let mut historical_vols = Vec::new();
         let window_size = 20;
+        let normalized = self.normalized_returns();
 
-        for i in window_size..self.returns.len() {
-            let window_returns: Vec<f64> = self.returns.iter()
-                .skip(i - window_size)
-                .take(window_size)
-                .cloned()
-                .collect();
-
-            let vol = self.calculate_window_volatility(&window_returns);
+        for i in window_size..normalized.len() {
+            let vol = self.calculate_window_volatility(&normalized[i - window_size..i]);
             historical_vols.push(vol);
         }
 
@@ -206,6 +655,7 @@ impl VolatilityModel {
         below_current as f64 / historical_vols.len() as f64
     }
 
+    // Annualized volatility of returns already scaled to one second
     fn calculate_window_volatility(&self, returns: &[f64]) -> f64 {
         if returns.len() < 2 {
             return 0.0;
@@ -216,7 +666,7 @@ impl VolatilityModel {
             .map(|r| (r - mean).powi(2))
             .sum::<f64>() / (returns.len() - 1) as f64;
 
-        variance.sqrt() * (252.0_f64).sqrt()
+        annualize(variance.sqrt())
     }
 
     pub fn forecast_volatility(&self, horizon_days: usize) -> f64 {
@@ -224,15 +674,13 @@ impl VolatilityModel {
             return self.realized_volatility;
         }
 
-        // Simple GARCH forecasting
-        let current_vol = self.realized_volatility;
-        let long_run_vol = self.garch_params.lambda.sqrt();
-
-        // Mean reversion forecast
-        let persistence = self.garch_params.alpha + self.garch_params.beta;
-        let decay_factor = persistence.powi(horizon_days as i32);
-
-        let forecast = long_run_vol + decay_factor * (current_vol - long_run_vol);
+        // The conditional variance mean-reverts to its long-run level at the
+        // model's persistence per observation, so convert the horizon to
+        // expected tick steps
+        let steps = horizon_days as f64 * TRADING_SECONDS_PER_DAY / self.average_return_interval();
+        let forecast_variance = self.garch_params
+            .forecast_variance(self.garch_state.conditional_variance, steps - 1.0);
+        let forecast = annualize(forecast_variance.max(0.0).sqrt());
         forecast.max(0.01) // Minimum volatility floor
     }
 
@@ -281,3 +729,248 @@ impl VolatilityModel {
         }
     }
 }
+
+impl BarBuilder {
+    fn update(&mut self, price: f64, timestamp: f64) {
+        let interval_ms = self.interval_secs * 1000.0;
+        let bar_start = (timestamp / interval_ms).floor() * interval_ms;
+
+        match self.current_bar.as_mut() {
+            Some(bar) if bar.start_time == bar_start => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(completed) = self.current_bar.take() {
+            self.bars.push_back(completed);
+            if self.bars.len() > self.max_bars {
+                self.bars.pop_front();
+            }
+        }
+        self.current_bar = Some(OhlcBar {
+            start_time: bar_start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+        });
+    }
+
+    // Per-second volatility from the latest completed bars
+    fn range_volatility(&self, estimator: VolatilityEstimator) -> Option<f64> {
+        let n = self.range_window.min(self.bars.len());
+        if n < 2 {
+            return None;
+        }
+        let bars: Vec<&OhlcBar> = self.bars.iter().skip(self.bars.len() - n).collect();
+
+        let bar_variance = match estimator {
+            VolatilityEstimator::Parkinson => mean(bars.iter().map(|b| parkinson_variance(b))),
+            VolatilityEstimator::GarmanKlass => mean(bars.iter().map(|b| garman_klass_variance(b))),
+            VolatilityEstimator::RogersSatchell => mean(bars.iter().map(|b| rogers_satchell_variance(b))),
+            VolatilityEstimator::YangZhang => {
+                // The overnight term needs the previous bar's close, so one bar is lost
+                if n < 3 {
+                    return None;
+                }
+                let overnight: Vec<f64> = bars.windows(2).map(|w| (w[1].open / w[0].close).ln()).collect();
+                let open_to_close: Vec<f64> = bars[1..].iter().map(|b| (b.close / b.open).ln()).collect();
+                let rogers_satchell = mean(bars[1..].iter().map(|b| rogers_satchell_variance(b)));
+                let m = open_to_close.len() as f64;
+                let k = 0.34 / (1.34 + (m + 1.0) / (m - 1.0));
+                sample_variance(&overnight) + k * sample_variance(&open_to_close) + (1.0 - k) * rogers_satchell
+            }
+            _ => return None,
+        };
+
+        Some((bar_variance.max(0.0) / self.interval_secs).sqrt())
+    }
+}
+
+// Two-scale realized variance (Zhang, Mykland and Ait-Sahalia): the average
+// sparse-grid RV over K offsets, bias-corrected with the full-grid RV
+fn two_scale_realized_variance(returns: &[f64]) -> f64 {
+    let n = returns.len();
+    let k = ((n as f64).powf(2.0 / 3.0).round() as usize).clamp(2, n / 2);
+
+    let mut log_price = Vec::with_capacity(n + 1);
+    log_price.push(0.0);
+    for r in returns {
+        log_price.push(log_price[log_price.len() - 1] + r);
+    }
+
+    let sparse_rv = log_price.windows(k + 1)
+        .map(|w| (w[k] - w[0]).powi(2))
+        .sum::<f64>() / k as f64;
+    let full_rv: f64 = returns.iter().map(|r| r * r).sum();
+    let n_bar = (n - k + 1) as f64 / k as f64;
+
+    (sparse_rv - n_bar / n as f64 * full_rv) / (1.0 - n_bar / n as f64)
+}
+
+// Non-flat-top realized kernel (Barndorff-Nielsen et al. 2011): Parzen weights at
+// h / (H + 1), which keep the estimate non-negative
+fn realized_kernel(returns: &[f64]) -> f64 {
+    let n = returns.len();
+    let bandwidth = ((n as f64).powf(0.6).ceil() as usize).clamp(1, n - 1);
+    let autocovariance = |h: usize| -> f64 {
+        returns[h..].iter().zip(returns.iter()).map(|(a, b)| a * b).sum()
+    };
+
+    (1..=bandwidth).fold(autocovariance(0), |kernel, h| {
+        kernel + 2.0 * parzen(h as f64 / (bandwidth + 1) as f64) * autocovariance(h)
+    })
+}
+
+fn parzen(x: f64) -> f64 {
+    if x <= 0.5 {
+        1.0 - 6.0 * x * x + 6.0 * x.powi(3)
+    } else if x <= 1.0 {
+        2.0 * (1.0 - x).powi(3)
+    } else {
+        0.0
+    }
+}
+
+// Pre-averaging (Jacod et al.): returns are smoothed over windows of about
+// sqrt(n) ticks with weight min(x, 1 - x), then the residual noise bias is removed
+fn pre_averaged_variance(returns: &[f64]) -> f64 {
+    let n = returns.len();
+    let k = ((n as f64).sqrt().ceil() as usize).clamp(2, n / 2);
+    let weight = |x: f64| x.min(1.0 - x);
+
+    let psi1 = k as f64 * (1..=k)
+        .map(|j| (weight(j as f64 / k as f64) - weight((j - 1) as f64 / k as f64)).powi(2))
+        .sum::<f64>();
+    let psi2 = (1..k).map(|j| weight(j as f64 / k as f64).powi(2)).sum::<f64>() / k as f64;
+
+    let pre_averaged_sum: f64 = returns.windows(k - 1)
+        .map(|w| {
+            w.iter().enumerate()
+                .map(|(j, r)| weight((j + 1) as f64 / k as f64) * r)
+                .sum::<f64>()
+                .powi(2)
+        })
+        .sum();
+    let full_rv: f64 = returns.iter().map(|r| r * r).sum();
+
+    n as f64 / (n - k + 2) as f64 * pre_averaged_sum / (k as f64 * psi2)
+        - psi1 * full_rv / (2.0 * (k * k) as f64 * psi2)
+}
+
+fn parkinson_variance(bar: &OhlcBar) -> f64 {
+    (bar.high / bar.low).ln().powi(2) / (4.0 * 2.0_f64.ln())
+}
+
+fn garman_klass_variance(bar: &OhlcBar) -> f64 {
+    0.5 * (bar.high / bar.low).ln().powi(2) - (2.0 * 2.0_f64.ln() - 1.0) * (bar.close / bar.open).ln().powi(2)
+}
+
+fn rogers_satchell_variance(bar: &OhlcBar) -> f64 {
+    (bar.high / bar.close).ln() * (bar.high / bar.open).ln() +
+        (bar.low / bar.close).ln() * (bar.low / bar.open).ln()
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    if count > 0 { sum / count as f64 } else { 0.0 }
+}
+
+fn sample_variance(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let m = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+// Per-second volatility to annualized
+pub fn annualize(per_second_volatility: f64) -> f64 {
+    per_second_volatility * (TRADING_DAYS_PER_YEAR * TRADING_SECONDS_PER_DAY).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One tick a second with i.i.d. normal log returns of `volatility` per second
+    fn gaussian_returns(seed: u64, count: usize, volatility: f64) -> Vec<f64> {
+        let mut rng = SimulationRng::new(seed);
+        (0..count).map(|_| volatility * rng.next_normal()).collect()
+    }
+
+    // Observed returns of an efficient log price with i.i.d. normal increments
+    // of `volatility`, each observation carrying i.i.d. noise of size `noise`
+    fn noisy_returns(seed: u64, count: usize, volatility: f64, noise: f64) -> Vec<f64> {
+        let mut rng = SimulationRng::new(seed);
+        let mut efficient = 0.0;
+        let mut observed = noise * rng.next_normal();
+        (0..count)
+            .map(|_| {
+                efficient += volatility * rng.next_normal();
+                let previous = observed;
+                observed = efficient + noise * rng.next_normal();
+                observed - previous
+            })
+            .collect()
+    }
+
+    // Mean of `estimator` over independent noisy paths, relative to the
+    // integrated variance of each path
+    fn mean_relative_estimate(estimator: fn(&[f64]) -> f64) -> f64 {
+        let (count, volatility, noise) = (5000, 1e-4, 2e-4);
+        let paths = 40;
+        (0..paths)
+            .map(|seed| estimator(&noisy_returns(100 + seed, count, volatility, noise)) / (count as f64 * volatility * volatility))
+            .sum::<f64>() / paths as f64
+    }
+
+    fn feed(model: &mut VolatilityModel, returns: &[f64]) {
+        let mut price = 100.0;
+        model.update(price, 0.0);
+        for (i, r) in returns.iter().enumerate() {
+            price *= r.exp();
+            model.update(price, (i + 1) as f64 * 1000.0);
+        }
+    }
+
+    #[test]
+    fn lee_mykland_flags_an_injected_jump() {
+        let volatility = 1e-3;
+        let mut returns = gaussian_returns(7, 400, volatility);
+        let clean = returns.clone();
+        // Five sigma is flagged only when the statistic and the Gumbel
+        // constants share the uncorrected bipower scale
+        returns[300] += 5.0 * volatility;
+
+        let mut model = VolatilityModel::new();
+        feed(&mut model, &returns);
+        let jumps = model.get_jump_statistics().recent_jumps;
+        assert_eq!(jumps.len(), 1, "{:?}", jumps);
+        assert_eq!(jumps[0].timestamp, 301_000.0);
+
+        let mut model = VolatilityModel::new();
+        feed(&mut model, &clean);
+        assert!(model.get_jump_statistics().recent_jumps.is_empty());
+    }
+
+    #[test]
+    fn noise_robust_estimators_remove_the_noise_bias() {
+        // Plain RV picks up 2 * n * noise^2, eight times the integrated variance here
+        let realized = mean_relative_estimate(|returns| returns.iter().map(|r| r * r).sum());
+        assert!(realized > 5.0, "realized variance {}", realized);
+
+        for (name, estimator) in [
+            ("two-scale", two_scale_realized_variance as fn(&[f64]) -> f64),
+            ("realized kernel", realized_kernel),
+            ("pre-averaging", pre_averaged_variance),
+        ] {
+            let relative = mean_relative_estimate(estimator);
+            assert!((relative - 1.0).abs() < 0.06, "{} averages {} of the integrated variance", name, relative);
+        }
+    }
+}